@@ -0,0 +1,289 @@
+use super::tool_calls::ToolCall;
+use crate::config::Config;
+use crate::permissions::{self, Decision, Permissions};
+use crate::tools::{DynTool, OutsideAccess, Workspace};
+use anyhow::{Result, bail};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio_util::sync::CancellationToken;
+
+/// Which tool calls need the user's approval before they run, from
+/// `tools.approval`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApprovalMode {
+    /// Ask before every call (`ask`).
+    Ask,
+    /// Run read-only tools; ask before writes and commands (`auto-read`).
+    AutoRead,
+    /// Never ask (`auto`).
+    Auto,
+}
+
+impl ApprovalMode {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.string("tools.approval").unwrap_or_default() {
+            "ask" => Ok(Self::Ask),
+            "auto-read" => Ok(Self::AutoRead),
+            "auto" => Ok(Self::Auto),
+            other => bail!("unknown tools.approval '{other}' (expected ask, auto-read or auto)"),
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Ask => "ask",
+            Self::AutoRead => "auto-read",
+            Self::Auto => "auto",
+        }
+    }
+}
+
+/// A tool call waiting for the user.
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    pub call_id: String,
+    pub name: String,
+    pub arguments: String,
+    /// The command, a diff, or the pretty-printed arguments.
+    pub preview: String,
+    /// What answering "always" allows, e.g. `bash(cargo test:*)`.
+    pub always: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    Approve,
+    /// Approve this call and, for the rest of the session, later calls
+    /// with the same scope: the same command prefixes for `bash`, the same
+    /// resolved path for the file tools, any call for other tools.
+    AlwaysAllow,
+    /// Skip the call; the reason, if any, is passed on to the model.
+    Deny(Option<String>),
+}
+
+/// Asks the user whether a tool call may run. Called on a blocking thread,
+/// one call at a time; once `cancel` fires the answer no longer matters and
+/// the implementation should return promptly.
+pub trait Approver: Send + Sync {
+    fn approve(&self, request: &ApprovalRequest, cancel: &CancellationToken) -> Approval;
+}
+
+/// What happens to a call once the rules and the user have had their say.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Verdict {
+    Run,
+    /// Run with access outside the workspace, which the user approved.
+    RunOutsideWorkspace,
+    /// Skip the call and send this output back instead.
+    Skip(String),
+}
+
+/// What an "always" answer covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Scope {
+    /// Every call to the tool.
+    Tool,
+    /// `bash` commands whose every part matches one of these patterns.
+    Commands(Vec<String>),
+    /// One `bash` command that no pattern could cover, as written.
+    Exact(String),
+    /// Calls on this resolved path.
+    Path(PathBuf),
+}
+
+/// A call the user allowed for the rest of the session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Grant {
+    tool: String,
+    scope: Scope,
+    /// Whether it covers paths outside the workspace.
+    outside: bool,
+}
+
+impl Grant {
+    fn for_call(name: &str, arguments: &Value, path: Option<PathBuf>, outside: bool) -> Self {
+        let scope = match (permissions::command(name, arguments), path) {
+            (Some(command), _) => permissions::command_grants(command)
+                .map_or_else(|| Scope::Exact(command.to_string()), Scope::Commands),
+            (None, Some(path)) => Scope::Path(path),
+            (None, None) => Scope::Tool,
+        };
+        Self {
+            tool: name.to_string(),
+            scope,
+            outside,
+        }
+    }
+
+    /// Whether this grant allows `call`, the grant a call would get.
+    fn covers(&self, call: &Self, arguments: &Value) -> bool {
+        if self.tool != call.tool || self.outside != call.outside {
+            return false;
+        }
+        match (&self.scope, permissions::command(&call.tool, arguments)) {
+            (Scope::Commands(patterns), Some(command)) => {
+                permissions::commands_match(patterns, command)
+            }
+            _ => self.scope == call.scope,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let target = match &self.scope {
+            Scope::Tool => self.tool.clone(),
+            Scope::Commands(patterns) => patterns
+                .iter()
+                .map(|pattern| format!("{}({pattern})", self.tool))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Scope::Exact(_) => format!("this exact {} command", self.tool),
+            Scope::Path(path) => format!("{} on {}", self.tool, path.display()),
+        };
+        if self.outside {
+            format!("{target} outside the workspace")
+        } else {
+            target
+        }
+    }
+}
+
+/// The permission rules, the approval mode, the workspace, who to ask,
+/// and the calls the user has allowed for the rest of the session.
+pub struct Approvals {
+    permissions: Permissions,
+    mode: ApprovalMode,
+    workspace: Workspace,
+    approver: Option<Arc<dyn Approver>>,
+    always_allowed: Mutex<Vec<Grant>>,
+}
+
+impl Approvals {
+    pub fn new(
+        permissions: Permissions,
+        mode: ApprovalMode,
+        workspace: Workspace,
+        approver: Option<Arc<dyn Approver>>,
+    ) -> Self {
+        Self {
+            permissions,
+            mode,
+            workspace,
+            approver,
+            always_allowed: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Apply the permission rules to `call`, then ask about it if no rule
+    /// decided and the mode requires it. A path outside the workspace is
+    /// asked about whatever the rules and the mode say, unless
+    /// `workspace.outside` refuses it outright.
+    pub(super) async fn check(
+        &self,
+        call: &ToolCall,
+        tool: Option<&Arc<dyn DynTool>>,
+        cancel: &CancellationToken,
+    ) -> Verdict {
+        let Some(tool) = tool.cloned() else {
+            return Verdict::Run;
+        };
+        let arguments: Value = serde_json::from_str(&call.arguments).unwrap_or_default();
+        let decision = self.permissions.check(&call.name, &arguments);
+        if let Some(denial) = decision.denial(&call.name) {
+            return Verdict::Skip(denial);
+        }
+        let path = tool
+            .path(&call.arguments)
+            .map(|path| (self.workspace.resolve(&path), path));
+        let resolved = path.as_ref().map(|(resolved, _)| match resolved {
+            Ok(resolved) | Err(resolved) => resolved.clone(),
+        });
+        let outside = path.and_then(|(resolved, path)| Some((path, resolved.err()?)));
+        let (verdict, reason) = if let Some((path, resolved)) = outside {
+            if self.workspace.outside_access() == OutsideAccess::Deny {
+                return Verdict::Skip(self.workspace.refusal(&path, &resolved));
+            }
+            let note = format!(
+                "{path} resolves to {}, outside the workspace",
+                resolved.display()
+            );
+            (Verdict::RunOutsideWorkspace, Some(note))
+        } else {
+            let needs_approval = matches!(decision, Decision::Unmatched)
+                && match self.mode {
+                    ApprovalMode::Ask => true,
+                    ApprovalMode::AutoRead => tool.is_mutating(),
+                    ApprovalMode::Auto => false,
+                };
+            if !needs_approval {
+                return Verdict::Run;
+            }
+            (Verdict::Run, None)
+        };
+        let grant = Grant::for_call(&call.name, &arguments, resolved, reason.is_some());
+        if self.is_always_allowed(&grant, &arguments) {
+            return verdict;
+        }
+        let Some(approver) = self.approver.clone() else {
+            let setting = if reason.is_some() {
+                "workspace.outside = \"ask\"".to_string()
+            } else {
+                format!("tools.approval = \"{}\"", self.mode.name())
+            };
+            return Verdict::Skip(format!(
+                "Error: {} needs the user's approval ({setting}), but no one is available to approve it",
+                call.name
+            ));
+        };
+
+        let mut request = ApprovalRequest {
+            call_id: call.call_id.clone(),
+            name: call.name.clone(),
+            arguments: call.arguments.clone(),
+            preview: String::new(),
+            always: grant.describe(),
+        };
+        let cancel = cancel.clone();
+        let approval = tokio::task::spawn_blocking(move || {
+            let preview = tool.preview(&request.arguments);
+            request.preview = match reason {
+                Some(reason) => format!("{reason}:\n{preview}"),
+                None => preview,
+            };
+            let approval = approver.approve(&request, &cancel);
+            // A cancelled call reports the cancellation when it "runs".
+            if cancel.is_cancelled() {
+                Approval::Approve
+            } else {
+                approval
+            }
+        })
+        .await;
+
+        match approval {
+            Ok(Approval::Approve) => verdict,
+            Ok(Approval::AlwaysAllow) => {
+                self.always_allowed
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(grant);
+                verdict
+            }
+            Ok(Approval::Deny(None)) => {
+                Verdict::Skip("Error: the user denied this tool call".to_string())
+            }
+            Ok(Approval::Deny(Some(reason))) => {
+                Verdict::Skip(format!("Error: the user denied this tool call: {reason}"))
+            }
+            Err(_) => Verdict::Skip("Error: the approval prompt failed".to_string()),
+        }
+    }
+
+    fn is_always_allowed(&self, call: &Grant, arguments: &Value) -> bool {
+        self.always_allowed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .any(|grant| grant.covers(call, arguments))
+    }
+}
@@ -0,0 +1,144 @@
+use super::Agent;
+use super::approval::{ApprovalMode, Approvals, Approver};
+use crate::app_context::AppContext;
+use crate::auth::AuthConfig;
+use crate::config::Config;
+use crate::permissions::Permissions;
+use crate::provider::{self, Provider, Reasoning};
+use crate::redact::Redactor;
+use crate::sandbox::Sandbox;
+use crate::session::{SessionManager, SessionStore};
+use crate::tools::{CommandEnv, DynTool, OutputLimits, Tool, ToolRegistry, Workspace};
+use crate::{api, prompt};
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Configures an [`Agent`]. Anything not set explicitly is derived from the
+/// config, which defaults to [`Config::defaults`].
+#[derive(Default)]
+pub struct AgentBuilder {
+    config: Option<Config>,
+    client: Option<reqwest::Client>,
+    auth: Option<AuthConfig>,
+    provider: Option<Box<dyn Provider>>,
+    tools: Option<ToolRegistry>,
+    extra_tools: Vec<Arc<dyn DynTool>>,
+    instructions: Option<String>,
+    approver: Option<Arc<dyn Approver>>,
+    session: Option<(Arc<dyn SessionStore>, String)>,
+}
+
+impl AgentBuilder {
+    #[must_use]
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    #[must_use]
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    #[must_use]
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    #[must_use]
+    pub fn provider(mut self, provider: Box<dyn Provider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Replace the built-in tools with `tools`.
+    #[must_use]
+    pub fn tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Add one tool on top of the tool set, replacing any tool with the
+    /// same name.
+    #[must_use]
+    pub fn tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.extra_tools.push(Arc::new(tool));
+        self
+    }
+
+    /// Replace the default system prompt.
+    #[must_use]
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Ask `approver` before running the calls `tools.approval` covers.
+    /// Without one, those calls are denied.
+    #[must_use]
+    pub fn approver(mut self, approver: impl Approver + 'static) -> Self {
+        self.approver = Some(Arc::new(approver));
+        self
+    }
+
+    /// Load `session_name` from `store` and persist every turn to it.
+    /// Without a store, history is kept in memory only.
+    #[must_use]
+    pub fn session(
+        mut self,
+        store: Arc<dyn SessionStore>,
+        session_name: impl Into<String>,
+    ) -> Self {
+        self.session = Some((store, session_name.into()));
+        self
+    }
+
+    pub fn build(self) -> Result<Agent> {
+        let config = self.config.unwrap_or_else(Config::defaults);
+        let auth = match self.auth {
+            Some(auth) => auth,
+            None => AuthConfig::from_config(&config)?,
+        };
+        let provider = self
+            .provider
+            .unwrap_or_else(|| provider::from_config(&config, &auth));
+        let mut tools = self.tools.unwrap_or_else(ToolRegistry::builtin);
+        for tool in self.extra_tools {
+            tools.insert(tool);
+        }
+        let session = match self.session {
+            Some((store, session_name)) => SessionManager::open(store, &session_name)?,
+            None => SessionManager::in_memory("in-memory"),
+        };
+
+        let instructions = self.instructions.unwrap_or_else(|| prompt::build(&tools));
+
+        let workspace = Workspace::from_config(&config)?;
+        let app = AppContext {
+            client: self.client.unwrap_or_default(),
+            auth,
+            provider,
+            tool_defs: tools.definitions(config.bool("tools.strict")),
+            tools,
+            approvals: Approvals::new(
+                Permissions::from_config(&config)?,
+                ApprovalMode::from_config(&config)?,
+                workspace.clone(),
+                self.approver,
+            ),
+            redactor: Redactor::from_config(&config)?,
+            instructions,
+            retry: api::RetryPolicy::from_config(&config),
+            reasoning: Reasoning::from_config(&config),
+            max_tool_calls: config.usize("tools.max_tool_calls"),
+            max_parallel_tools: config.usize("tools.max_parallel"),
+            output_limits: OutputLimits::from_config(&config),
+            sandbox: Sandbox::from_config(&config)?,
+            command_env: CommandEnv::from_config(&config),
+            workspace,
+        };
+        Ok(Agent { app, session })
+    }
+}
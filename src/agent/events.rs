@@ -1,37 +1,50 @@
-use serde::Deserialize;
+use crate::provider::Usage;
+use std::time::Duration;
 
-#[derive(Deserialize, Debug)]
-#[serde(tag = "type")]
-pub(super) enum StreamEvent {
-    #[serde(rename = "response.output_item.added")]
-    OutputItemAdded { item: OutputItem },
-
-    #[serde(rename = "response.output_text.delta")]
-    TextDelta { delta: String },
-
-    #[serde(rename = "response.output_item.done")]
-    OutputItemDone { item: OutputItem },
-
-    #[serde(other)]
-    Ignored,
-}
-
-#[derive(Deserialize, Debug)]
-pub(super) struct OutputItem {
-    #[serde(rename = "type")]
-    pub(super) item_type: String,
-    #[serde(default)]
-    pub(super) name: Option<String>,
-    #[serde(default)]
-    pub(super) call_id: Option<String>,
-    #[serde(default)]
-    pub(super) arguments: Option<String>,
-    #[serde(default)]
-    pub(super) content: Vec<OutputContentPart>,
+/// Everything a frontend can observe while a turn runs, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentEvent {
+    TurnStarted,
+    TextDelta(String),
+    /// A complete assistant message; follows its text deltas.
+    MessageFinished {
+        text: String,
+    },
+    ToolCallStarted {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    ToolCallFinished {
+        call_id: String,
+        name: String,
+        output: String,
+        duration: Duration,
+    },
+    /// Token usage of one model call.
+    Usage(Usage),
+    Error(String),
+    /// Something went wrong that did not fail the turn.
+    Warning(String),
+    /// A model request failed for `reason` and is retried once `remaining`
+    /// has passed. Sent again every second while waiting, so a frontend
+    /// can show a countdown.
+    Retrying {
+        reason: String,
+        attempt: u32,
+        max_attempts: u32,
+        remaining: Duration,
+    },
+    /// Always the last event of a turn, whether or not it failed.
+    /// `hit_tool_limit` means the turn used up `tools.max_tool_calls` model
+    /// calls while the model still wanted to call tools.
+    TurnFinished {
+        hit_tool_limit: bool,
+    },
 }
 
-#[derive(Deserialize, Debug)]
-pub(super) struct OutputContentPart {
-    #[serde(default)]
-    pub(super) text: Option<String>,
+/// Receives the events of a turn. The REPL renders them to the terminal;
+/// headless mode turns them into text or JSON.
+pub trait EventSink: Send {
+    fn on_event(&mut self, event: &AgentEvent);
 }
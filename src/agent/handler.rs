@@ -1,85 +1,121 @@
-use super::events::{OutputItem, StreamEvent};
-use crate::tools;
-use anyhow::Result;
-use std::io::{self, Write};
+use super::events::{AgentEvent, EventSink};
+use super::tool_calls::ToolCall;
+use crate::provider::{ProviderEvent, StreamError};
+use crate::session::DIAGNOSTIC_TYPE;
+use crate::tools::{self, ToolRegistry};
 
+/// Applies provider events to the history and forwards them to the sink.
 pub(super) struct EventHandler<'a> {
     history: &'a mut Vec<serde_json::Value>,
-    has_tool_calls: bool,
+    sink: &'a mut dyn EventSink,
+    tools: &'a ToolRegistry,
+    tool_calls: Vec<ToolCall>,
+    partial_text: String,
+    error: Option<StreamError>,
 }
 
 impl<'a> EventHandler<'a> {
-    pub(super) const fn new(history: &'a mut Vec<serde_json::Value>) -> Self {
+    pub(super) fn new(
+        history: &'a mut Vec<serde_json::Value>,
+        sink: &'a mut dyn EventSink,
+        tools: &'a ToolRegistry,
+    ) -> Self {
         Self {
             history,
-            has_tool_calls: false,
+            sink,
+            tools,
+            tool_calls: Vec::new(),
+            partial_text: String::new(),
+            error: None,
         }
     }
 
-    pub(super) fn handle_event(&mut self, event: StreamEvent) -> Result<()> {
+    pub(super) fn handle_event(&mut self, event: ProviderEvent) {
         match event {
-            StreamEvent::OutputItemAdded { item } => Self::handle_output_item_added(&item),
-            StreamEvent::TextDelta { delta } => Self::handle_text_delta(&delta)?,
-            StreamEvent::OutputItemDone { item } => self.handle_output_item_done(&item),
-            StreamEvent::Ignored => {}
+            // Calls are reported once their arguments are complete.
+            ProviderEvent::ToolCallStarted { .. } => {}
+            ProviderEvent::TextDelta(delta) => self.handle_text_delta(delta),
+            ProviderEvent::MessageDone { text } => self.handle_message_done(text),
+            ProviderEvent::ToolCallDone {
+                call_id,
+                name,
+                arguments,
+            } => self.handle_tool_call_done(call_id, name, arguments),
+            ProviderEvent::Usage(usage) => self.sink.on_event(&AgentEvent::Usage(usage)),
+            ProviderEvent::Error(error) => self.error = Some(error),
+            ProviderEvent::Warning(warning) => self.sink.on_event(&AgentEvent::Warning(warning)),
         }
+    }
 
-        Ok(())
+    /// The error event that ended the stream, if one arrived.
+    pub(super) const fn take_error(&mut self) -> Option<StreamError> {
+        self.error.take()
     }
 
-    pub(super) const fn has_tool_calls(&self) -> bool {
-        self.has_tool_calls
+    pub(super) fn into_tool_calls(self) -> Vec<ToolCall> {
+        self.tool_calls
     }
 
-    fn handle_output_item_added(item: &OutputItem) {
-        if item.item_type == "function_call" {
-            println!("Calling {}...", item.name.as_deref().unwrap_or("unknown"));
+    /// Keep assistant text that was streamed but never completed, marked so
+    /// the model can tell the reply was cut short.
+    pub(super) fn finish_interrupted(&mut self) {
+        if self.partial_text.is_empty() {
+            return;
         }
+        let text = std::mem::take(&mut self.partial_text);
+        self.history.push(serde_json::json!({
+            "role": "assistant",
+            "content": format!("{text}\n\n[interrupted by user]")
+        }));
     }
 
-    fn handle_text_delta(delta: &str) -> Result<()> {
-        print!("{delta}");
-        io::stdout().flush()?;
-        Ok(())
+    fn handle_text_delta(&mut self, delta: String) {
+        self.partial_text.push_str(&delta);
+        self.sink.on_event(&AgentEvent::TextDelta(delta));
     }
 
-    fn handle_output_item_done(&mut self, item: &OutputItem) {
-        match item.item_type.as_str() {
-            "message" => self.handle_output_message(item),
-            "function_call" => self.handle_output_function_call(item),
-            _ => {}
-        }
-    }
-
-    fn handle_output_message(&mut self, item: &OutputItem) {
-        println!();
-        let text = item
-            .content
-            .first()
-            .and_then(|part| part.text.as_deref())
-            .unwrap_or("");
+    fn handle_message_done(&mut self, text: String) {
+        self.partial_text.clear();
         self.history.push(serde_json::json!({
             "role": "assistant",
             "content": text
         }));
+        self.sink.on_event(&AgentEvent::MessageFinished { text });
     }
 
-    fn handle_output_function_call(&mut self, item: &OutputItem) {
-        let call_id = item.call_id.as_deref().unwrap_or("");
-        let name = item.name.as_deref().unwrap_or("");
-        let arguments = item.arguments.as_deref().unwrap_or("");
-        let result = tools::execute(name, arguments);
+    /// Record a finished call, repairing malformed arguments first so the
+    /// history replays as valid JSON. Repairs are logged as a diagnostic
+    /// entry with the original text. Truncated arguments to a mutating tool
+    /// are left as they are, so the call fails and the model resends them
+    /// instead of a guess being written.
+    fn handle_tool_call_done(&mut self, call_id: String, name: String, arguments: String) {
+        let complete_truncated = !self.tools.is_mutating(&name);
+        let (arguments, repairs, original) =
+            match tools::repair_arguments(&arguments, complete_truncated) {
+                Some(repair) => (repair.arguments, repair.fixes, Some(arguments)),
+                None => (arguments, Vec::new(), None),
+            };
         self.history.push(serde_json::json!({
             "type": "function_call",
             "call_id": call_id,
             "name": name,
             "arguments": arguments
         }));
-        self.history.push(serde_json::json!({
-            "type": "function_call_output",
-            "call_id": call_id,
-            "output": result
-        }));
-        self.has_tool_calls = true;
+        if let Some(original) = original {
+            self.history.push(serde_json::json!({
+                "type": DIAGNOSTIC_TYPE,
+                "event": "arguments_repaired",
+                "call_id": call_id,
+                "name": name,
+                "fixes": repairs,
+                "original": original
+            }));
+        }
+        self.tool_calls.push(ToolCall {
+            call_id,
+            name,
+            arguments,
+            repairs,
+        });
     }
 }
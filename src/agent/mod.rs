@@ -1,52 +1,272 @@
+pub(crate) mod approval;
+mod builder;
 mod events;
 mod handler;
-mod stream;
+pub(crate) mod stream;
 #[cfg(test)]
 mod stream_tests;
+mod tool_calls;
+#[cfg(test)]
+mod tool_calls_tests;
 
 use crate::api;
 use crate::app_context::AppContext;
-use anyhow::Result;
+use crate::auth::AuthConfig;
+use crate::provider::StreamError;
+use crate::session::SessionManager;
+use crate::tools::ToolContext;
+use anyhow::{Result, bail};
 use futures::StreamExt;
+use futures::stream::{self as futures_stream, BoxStream};
 use handler::EventHandler;
-use stream::{get_event, parse_event};
+use std::time::Duration;
+use stream::get_event;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tool_calls::ToolCall;
+
+pub use approval::{Approval, ApprovalMode, ApprovalRequest, Approver};
+pub use builder::AgentBuilder;
+pub use events::{AgentEvent, EventSink};
+
+/// How long tools get to stop after a turn is cancelled before the turn
+/// gives up waiting for them.
+const INTERRUPT_GRACE: Duration = Duration::from_secs(2);
+
+/// A model, its tools and one session's history.
+pub struct Agent {
+    app: AppContext,
+    session: SessionManager,
+}
+
+impl Agent {
+    pub fn builder() -> AgentBuilder {
+        AgentBuilder::default()
+    }
+
+    pub const fn auth(&self) -> &AuthConfig {
+        &self.app.auth
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.app.provider.name()
+    }
+
+    pub fn session_name(&self) -> &str {
+        self.session.session_name()
+    }
+
+    pub fn history(&self) -> &[serde_json::Value] {
+        self.session.history()
+    }
+
+    /// Run one user turn, yielding its events as they happen. The stream
+    /// always starts with `TurnStarted` and ends after `TurnFinished`.
+    /// Cancelling `cancel` stops the model call and running tools; the turn
+    /// then finishes with an error event.
+    pub fn run_turn<'a>(
+        &'a mut self,
+        input: &str,
+        cancel: &CancellationToken,
+    ) -> BoxStream<'a, AgentEvent> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let input = input.to_string();
+        let cancel = cancel.clone();
+        let driver = async move {
+            let mut sink = ChannelSink(sender);
+            self.run_turn_with_sink(&input, &cancel, &mut sink).await;
+        };
+        let events = futures_stream::poll_fn(move |cx| receiver.poll_recv(cx));
+        let driver = futures_stream::once(driver).filter_map(|()| async { None });
+        futures_stream::select(events, driver).boxed()
+    }
+
+    /// Like [`Agent::run_turn`], but pushes events into `sink` and returns
+    /// once the turn has finished and been persisted.
+    pub async fn run_turn_with_sink(
+        &mut self,
+        input: &str,
+        cancel: &CancellationToken,
+        sink: &mut dyn EventSink,
+    ) {
+        sink.on_event(&AgentEvent::TurnStarted);
+        for warning in self.session.take_warnings() {
+            sink.on_event(&AgentEvent::Warning(warning));
+        }
+        let user_entry = serde_json::json!({
+            "role": "user",
+            "content": input
+        });
+        if let Err(e) = self.session.append(user_entry) {
+            sink.on_event(&AgentEvent::Error(format!("{e:#}")));
+            sink.on_event(&AgentEvent::TurnFinished {
+                hit_tool_limit: false,
+            });
+            return;
+        }
+
+        let persist_start = self.session.history_len();
+        let result = run(&self.app, self.session.history_mut(), cancel, sink).await;
+        self.session.close_dangling_calls(persist_start);
+        if let Err(e) = &result {
+            sink.on_event(&AgentEvent::Error(format!("{e:#}")));
+        }
+        if let Err(e) = self.session.persist_from(persist_start) {
+            sink.on_event(&AgentEvent::Warning(format!(
+                "failed to persist session entries for {}: {e:#}",
+                self.session.session_name()
+            )));
+        }
+        sink.on_event(&AgentEvent::TurnFinished {
+            hit_tool_limit: matches!(result, Ok(true)),
+        });
+    }
+}
 
-const MAX_TOOL_CALLS: usize = 20;
+struct ChannelSink(mpsc::UnboundedSender<AgentEvent>);
 
-pub async fn run(
+impl EventSink for ChannelSink {
+    fn on_event(&mut self, event: &AgentEvent) {
+        // The receiver only goes away when the caller drops the stream.
+        let _ = self.0.send(event.clone());
+    }
+}
+
+/// Call the model and execute its tool calls until it answers without
+/// calling tools. Returns whether the turn stopped at the tool-call limit.
+async fn run(
     app: &AppContext,
     history: &mut Vec<serde_json::Value>,
-) -> Result<()> {
-    for _ in 0..MAX_TOOL_CALLS {
-        let response = api::call_openai(app, history).await?;
-        let has_tool_calls = stream_response(response, history).await?;
-        if !has_tool_calls {
-            break;
+    cancel: &CancellationToken,
+    sink: &mut dyn EventSink,
+) -> Result<bool> {
+    let ctx = ToolContext {
+        cancel: cancel.clone(),
+        limits: app.output_limits,
+        sandbox: app.sandbox.clone(),
+        env: app.command_env.clone(),
+        workspace: app.workspace.clone(),
+    };
+    for _ in 0..app.max_tool_calls {
+        let tool_calls = call_model(app, history, cancel, sink).await?;
+        if tool_calls.is_empty() {
+            return Ok(false);
+        }
+
+        let execution = tool_calls::execute_all(
+            &tool_calls,
+            &app.tools,
+            &app.approvals,
+            &app.redactor,
+            &ctx,
+            app.max_parallel_tools,
+            sink,
+        );
+        let outputs = tokio::select! {
+            outputs = execution => outputs?,
+            () = abandon_after_grace(cancel) => {
+                bail!("interrupted by user; tools did not stop in time and were abandoned")
+            }
+        };
+        for (call, output) in tool_calls.iter().zip(outputs) {
+            history.push(serde_json::json!({
+                "type": "function_call_output",
+                "call_id": call.call_id,
+                "output": output
+            }));
+        }
+        if cancel.is_cancelled() {
+            bail!("interrupted by user");
         }
     }
-    Ok(())
+    Ok(true)
+}
+
+/// One model call, streamed into `history`. A retryable error event in
+/// the stream (an overloaded API) discards what the stream added and
+/// makes the request again, with the same backoff as a failed request.
+async fn call_model(
+    app: &AppContext,
+    history: &mut Vec<serde_json::Value>,
+    cancel: &CancellationToken,
+    sink: &mut dyn EventSink,
+) -> Result<Vec<ToolCall>> {
+    let mut attempt = 1;
+    loop {
+        let response = tokio::select! {
+            response = api::send_model_request(app, history, sink) => response?,
+            () = cancel.cancelled() => bail!("interrupted by user"),
+        };
+        let start = history.len();
+        let error = match stream_response(app, response, history, cancel, sink).await {
+            Ok(tool_calls) => return Ok(tool_calls),
+            Err(error) => error,
+        };
+        let retryable = error
+            .downcast_ref::<StreamError>()
+            .is_some_and(|error| error.retryable);
+        if !retryable || attempt >= app.retry.max_attempts() {
+            return Err(error);
+        }
+        history.truncate(start);
+        let reason = error.to_string();
+        tokio::select! {
+            () = api::backoff(&app.retry, attempt, &reason, sink) => {}
+            () = cancel.cancelled() => bail!("interrupted by user"),
+        }
+        attempt += 1;
+    }
 }
 
 async fn stream_response(
+    app: &AppContext,
     response: reqwest::Response,
     history: &mut Vec<serde_json::Value>,
-) -> Result<bool> {
+    cancel: &CancellationToken,
+    sink: &mut dyn EventSink,
+) -> Result<Vec<ToolCall>> {
     let mut stream = response.bytes_stream();
     let mut buffer = String::new();
-    let mut event_handler = EventHandler::new(history);
+    let mut parser = app.provider.stream_parser();
+    let mut event_handler = EventHandler::new(history, sink, &app.tools);
 
-    while let Some(chunk) = stream.next().await {
+    loop {
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            () = cancel.cancelled() => {
+                event_handler.finish_interrupted();
+                bail!("interrupted by user");
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
         let chunk = chunk?;
         buffer.push_str(&String::from_utf8_lossy(&chunk));
 
         while let Some(data) = get_event(&mut buffer) {
-            let Some(event) = parse_event(&data) else {
-                continue;
-            };
-
-            event_handler.handle_event(event)?;
+            for event in parser.parse(&data) {
+                event_handler.handle_event(event);
+            }
         }
+        if let Some(error) = event_handler.take_error() {
+            return Err(error.into());
+        }
+    }
+
+    for event in parser.finish() {
+        event_handler.handle_event(event);
     }
+    if let Some(error) = event_handler.take_error() {
+        return Err(error.into());
+    }
+
+    Ok(event_handler.into_tool_calls())
+}
 
-    Ok(event_handler.has_tool_calls())
+/// Resolves once `cancel` has fired and tools have had `INTERRUPT_GRACE`
+/// to notice.
+async fn abandon_after_grace(cancel: &CancellationToken) {
+    cancel.cancelled().await;
+    tokio::time::sleep(INTERRUPT_GRACE).await;
 }
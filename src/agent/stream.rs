@@ -1,17 +1,4 @@
-use super::events::StreamEvent;
-
-pub(super) fn parse_event(data: &str) -> Option<StreamEvent> {
-    match serde_json::from_str(data) {
-        Ok(event) => Some(event),
-        Err(err) => {
-            let preview: String = data.chars().take(200).collect();
-            eprintln!("Warning: failed to parse SSE event JSON: {err}; payload: {preview}");
-            None
-        }
-    }
-}
-
-pub(super) fn get_event(buffer: &mut String) -> Option<String> {
+pub fn get_event(buffer: &mut String) -> Option<String> {
     loop {
         let pos = buffer.find("\n\n")?;
         let event_text = buffer[..pos].to_string();
@@ -1,5 +1,4 @@
-use super::events::StreamEvent;
-use super::stream::{get_event, parse_event};
+use super::stream::get_event;
 
 #[test]
 fn get_event_returns_first_data_payload() {
@@ -38,16 +37,3 @@ fn get_event_joins_multi_line_data() {
     );
     assert!(buffer.is_empty());
 }
-
-#[test]
-fn parse_event_rejects_invalid_json() {
-    let result = parse_event("{not-json");
-    assert!(result.is_none());
-}
-
-#[test]
-fn parse_event_accepts_known_event() {
-    let data = r#"{"type":"response.output_text.delta","delta":"hi"}"#;
-    let event = parse_event(data).expect("event should parse");
-    assert!(matches!(event, StreamEvent::TextDelta { .. }));
-}
@@ -0,0 +1,148 @@
+use super::approval::{Approvals, Verdict};
+use super::events::{AgentEvent, EventSink};
+use crate::redact::Redactor;
+use crate::tools::{self, ToolContext, ToolRegistry};
+use anyhow::{Context, Result};
+use futures::{StreamExt, stream};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub(super) struct ToolCall {
+    pub(super) call_id: String,
+    pub(super) name: String,
+    pub(super) arguments: String,
+    /// Fixes applied to malformed arguments before the call was recorded.
+    pub(super) repairs: Vec<&'static str>,
+}
+
+/// Run every call and return the outputs in the original call order.
+///
+/// Consecutive read-only calls share a bounded worker pool. A mutating call
+/// runs on its own, so writes are never reordered against other calls.
+/// Tools run on blocking threads and observe `ctx.cancel` themselves; calls
+/// not yet started when it fires return a cancellation error instead.
+///
+/// Each batch reports its calls as started before it runs and as finished,
+/// in call order, once the whole batch is done. Calls that need approval
+/// are asked about one by one after the start events; denied calls finish
+/// with the denial instead of running. Outputs are redacted before they
+/// are reported, with a warning for each call that had secrets removed.
+pub(super) async fn execute_all(
+    calls: &[ToolCall],
+    registry: &ToolRegistry,
+    approvals: &Approvals,
+    redactor: &Redactor,
+    ctx: &ToolContext,
+    max_parallel: usize,
+    sink: &mut dyn EventSink,
+) -> Result<Vec<String>> {
+    let mut outputs = Vec::with_capacity(calls.len());
+    for batch in batches(calls, registry) {
+        for call in batch {
+            sink.on_event(&AgentEvent::ToolCallStarted {
+                call_id: call.call_id.clone(),
+                name: call.name.clone(),
+                arguments: call.arguments.clone(),
+            });
+        }
+        let mut verdicts = Vec::with_capacity(batch.len());
+        for call in batch {
+            let tool = registry.get(&call.name);
+            verdicts.push(approvals.check(call, tool, &ctx.cancel).await);
+        }
+        let jobs: Vec<_> = batch
+            .iter()
+            .zip(verdicts)
+            .map(|(call, verdict)| async move {
+                match verdict {
+                    Verdict::Run => execute_one(call, registry, redactor, ctx.clone()).await,
+                    Verdict::RunOutsideWorkspace => {
+                        let ctx = ToolContext {
+                            workspace: ctx.workspace.allowing_outside(),
+                            ..ctx.clone()
+                        };
+                        execute_one(call, registry, redactor, ctx).await
+                    }
+                    Verdict::Skip(output) => Ok((output, Duration::ZERO, 0)),
+                }
+            })
+            .collect();
+        let results: Vec<Result<(String, Duration, usize)>> =
+            stream::iter(jobs).buffered(max_parallel).collect().await;
+        for (call, result) in batch.iter().zip(results) {
+            let (output, duration, redacted) = result?;
+            if redacted > 0 {
+                sink.on_event(&AgentEvent::Warning(format!(
+                    "redacted {redacted} value(s) that looked like secrets from the {} output",
+                    call.name
+                )));
+            }
+            sink.on_event(&AgentEvent::ToolCallFinished {
+                call_id: call.call_id.clone(),
+                name: call.name.clone(),
+                output: output.clone(),
+                duration,
+            });
+            outputs.push(output);
+        }
+    }
+    Ok(outputs)
+}
+
+/// Run one call and redact its output. Returns the output, how long the
+/// call took and how many values were redacted.
+async fn execute_one(
+    call: &ToolCall,
+    registry: &ToolRegistry,
+    redactor: &Redactor,
+    ctx: ToolContext,
+) -> Result<(String, Duration, usize)> {
+    let tool = registry.get(&call.name).cloned();
+    let name = call.name.clone();
+    // Redacted values the model copied into a write go back to the real
+    // ones; read-only tools keep the placeholders.
+    let arguments = if registry.is_mutating(&call.name) {
+        redactor.restore(&call.arguments)
+    } else {
+        call.arguments.clone()
+    };
+    let started = Instant::now();
+    let output = tokio::task::spawn_blocking(move || {
+        tools::execute(tool.as_deref(), &name, &arguments, &ctx)
+    })
+    .await
+    .with_context(|| format!("tool '{}' panicked", call.name))?;
+    let duration = started.elapsed();
+    // The model reads failures as text like any other output.
+    let output = output.unwrap_or_else(|error| error);
+    let (mut output, redactions) = redactor.redact_output(&output);
+    if !call.repairs.is_empty() {
+        let note = format!(
+            "Note: the arguments were not valid JSON and were repaired ({}); check that the result is what you intended.",
+            call.repairs.join(", ")
+        );
+        output = format!("{note}\n{output}");
+    }
+    Ok((output, duration, redactions))
+}
+
+pub(super) fn batches<'a>(calls: &'a [ToolCall], registry: &ToolRegistry) -> Vec<&'a [ToolCall]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+
+    for (index, call) in calls.iter().enumerate() {
+        if !registry.is_mutating(&call.name) {
+            continue;
+        }
+        if start < index {
+            batches.push(&calls[start..index]);
+        }
+        batches.push(&calls[index..=index]);
+        start = index + 1;
+    }
+
+    if start < calls.len() {
+        batches.push(&calls[start..]);
+    }
+    batches
+}
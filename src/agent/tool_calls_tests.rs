@@ -0,0 +1,445 @@
+use super::approval::{Approval, ApprovalMode, ApprovalRequest, Approvals, Approver};
+use super::events::AgentEvent;
+use super::handler::EventHandler;
+use super::tool_calls::{ToolCall, batches, execute_all};
+use crate::permissions::Permissions;
+use crate::provider::ProviderEvent;
+use crate::redact::Redactor;
+use crate::test_support::Recorder;
+use crate::tools::{OutsideAccess, Tool, ToolContext, ToolRegistry, Workspace};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+fn call(name: &str, arguments: &str) -> ToolCall {
+    ToolCall {
+        call_id: format!("call_{name}"),
+        name: name.to_string(),
+        arguments: arguments.to_string(),
+        repairs: Vec::new(),
+    }
+}
+
+async fn run_recorded(calls: &[ToolCall], cancel: &CancellationToken) -> (Vec<String>, Recorder) {
+    let ctx = ToolContext {
+        cancel: cancel.clone(),
+        ..ToolContext::default()
+    };
+    let mut recorder = Recorder::default();
+    let approvals = Approvals::new(
+        Permissions::default(),
+        ApprovalMode::Auto,
+        Workspace::default(),
+        None,
+    );
+    let outputs = execute_all(
+        calls,
+        &ToolRegistry::builtin(),
+        &approvals,
+        &Redactor::default(),
+        &ctx,
+        8,
+        &mut recorder,
+    )
+    .await
+    .expect("calls should run");
+    (outputs, recorder)
+}
+
+async fn run_all(calls: &[ToolCall], cancel: &CancellationToken) -> Vec<String> {
+    run_recorded(calls, cancel).await.0
+}
+
+fn batch_names(calls: &[ToolCall]) -> Vec<Vec<&str>> {
+    batches(calls, &ToolRegistry::builtin())
+        .into_iter()
+        .map(|batch| batch.iter().map(|call| call.name.as_str()).collect())
+        .collect()
+}
+
+#[test]
+fn batches_group_consecutive_read_only_calls() {
+    let calls = vec![
+        call("read_file", "{}"),
+        call("grep", "{}"),
+        call("ls", "{}"),
+    ];
+
+    assert_eq!(batch_names(&calls), vec![vec!["read_file", "grep", "ls"]]);
+}
+
+#[test]
+fn batches_isolate_mutating_calls() {
+    let calls = vec![
+        call("read_file", "{}"),
+        call("write_file", "{}"),
+        call("edit", "{}"),
+        call("ls", "{}"),
+        call("bash", "{}"),
+    ];
+
+    assert_eq!(
+        batch_names(&calls),
+        vec![
+            vec!["read_file"],
+            vec!["write_file"],
+            vec!["edit"],
+            vec!["ls"],
+            vec!["bash"],
+        ]
+    );
+}
+
+#[tokio::test]
+async fn execute_all_preserves_call_order() {
+    let calls = vec![
+        call("unknown_a", "{}"),
+        call("unknown_b", "{}"),
+        call("unknown_c", "{}"),
+    ];
+
+    let outputs = run_all(&calls, &CancellationToken::new()).await;
+
+    assert_eq!(
+        outputs,
+        vec![
+            "Unknown tool: unknown_a",
+            "Unknown tool: unknown_b",
+            "Unknown tool: unknown_c",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn execute_all_stops_bash_on_cancel() {
+    let calls = vec![call("bash", r#"{"command":"sleep 30 & sleep 30"}"#)];
+    let cancel = CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        trigger.cancel();
+    });
+
+    let start = Instant::now();
+    let outputs = run_all(&calls, &cancel).await;
+
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(outputs[0].starts_with("Error: command cancelled by user"));
+}
+
+#[tokio::test]
+async fn execute_all_skips_calls_after_cancel() {
+    let calls = vec![call("read_file", r#"{"path":"Cargo.toml"}"#)];
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    let outputs = run_all(&calls, &cancel).await;
+
+    assert_eq!(outputs, vec!["Error: tool call cancelled by user"]);
+}
+
+#[tokio::test]
+async fn execute_all_reports_each_batch_as_started_then_finished() {
+    let calls = vec![
+        call("unknown_a", "{}"),
+        call("unknown_b", "{}"),
+        call("bash", r#"{"command":"true"}"#),
+    ];
+
+    let (_, recorder) = run_recorded(&calls, &CancellationToken::new()).await;
+
+    let summary: Vec<String> = recorder
+        .0
+        .iter()
+        .map(|event| match event {
+            AgentEvent::ToolCallStarted { name, .. } => format!("start {name}"),
+            AgentEvent::ToolCallFinished { name, .. } => format!("finish {name}"),
+            other => format!("{other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            "start unknown_a",
+            "start unknown_b",
+            "finish unknown_a",
+            "finish unknown_b",
+            "start bash",
+            "finish bash",
+        ]
+    );
+}
+
+struct Shout;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct ShoutArgs {
+    text: String,
+}
+
+impl Tool for Shout {
+    type Args = ShoutArgs;
+
+    fn name(&self) -> &'static str {
+        "shout"
+    }
+
+    fn description(&self) -> &'static str {
+        "Upper-case some text."
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn run(&self, args: ShoutArgs, _ctx: &ToolContext) -> Result<String, String> {
+        Ok(args.text.to_uppercase())
+    }
+}
+
+#[tokio::test]
+async fn execute_all_runs_registered_tools() {
+    let mut registry = ToolRegistry::builtin();
+    registry.register(Shout);
+    let calls = vec![call("read_file", "{}"), call("shout", r#"{"text":"hi"}"#)];
+
+    let outputs = execute_all(
+        &calls,
+        &registry,
+        &Approvals::new(
+            Permissions::default(),
+            ApprovalMode::Auto,
+            Workspace::default(),
+            None,
+        ),
+        &Redactor::default(),
+        &ToolContext::default(),
+        8,
+        &mut Recorder::default(),
+    )
+    .await
+    .expect("calls should run");
+
+    assert_eq!(batches(&calls, &registry).len(), 2);
+    assert_eq!(outputs[1], "HI");
+}
+
+#[tokio::test]
+async fn truncated_write_file_arguments_are_not_completed() {
+    let path = std::env::temp_dir().join(format!("ox-truncated-{:016x}.txt", fastrand::u64(..)));
+    std::fs::write(&path, "original").expect("write file");
+    let full = serde_json::json!({ "path": path, "content": "new content" }).to_string();
+    let truncated = &full[..full.len() - "content\"}".len()];
+    let mut history = Vec::new();
+    let mut recorder = Recorder::default();
+    let registry = ToolRegistry::builtin();
+    let mut handler = EventHandler::new(&mut history, &mut recorder, &registry);
+    handler.handle_event(ProviderEvent::ToolCallDone {
+        call_id: "call_1".to_string(),
+        name: "write_file".to_string(),
+        arguments: truncated.to_string(),
+    });
+    let calls = handler.into_tool_calls();
+
+    let outputs = run_all(&calls, &CancellationToken::new()).await;
+
+    assert!(calls[0].repairs.is_empty());
+    assert!(
+        outputs[0].starts_with("Error: invalid JSON in arguments for write_file"),
+        "{}",
+        outputs[0]
+    );
+    assert!(outputs[0].contains("Expected arguments matching this schema"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "original");
+    let _ = std::fs::remove_file(path);
+}
+
+/// Answers with the next scripted approval and records what it was asked.
+struct Scripted {
+    answers: Mutex<Vec<Approval>>,
+    asked: Mutex<Vec<String>>,
+}
+
+impl Approver for Scripted {
+    fn approve(&self, request: &ApprovalRequest, _cancel: &CancellationToken) -> Approval {
+        self.asked.lock().unwrap().push(request.preview.clone());
+        self.answers.lock().unwrap().remove(0)
+    }
+}
+
+async fn run_approved(calls: &[ToolCall], approvals: &Approvals) -> Vec<String> {
+    execute_all(
+        calls,
+        &ToolRegistry::builtin(),
+        approvals,
+        &Redactor::default(),
+        &ToolContext::default(),
+        8,
+        &mut Recorder::default(),
+    )
+    .await
+    .expect("calls should run")
+}
+
+#[tokio::test]
+async fn execute_all_asks_before_mutating_calls() {
+    let approver = Arc::new(Scripted {
+        answers: Mutex::new(vec![
+            Approval::Deny(Some("use printf".to_string())),
+            Approval::AlwaysAllow,
+            Approval::Deny(None),
+        ]),
+        asked: Mutex::new(Vec::new()),
+    });
+    let approvals = Approvals::new(
+        Permissions::default(),
+        ApprovalMode::AutoRead,
+        Workspace::default(),
+        Some(approver.clone()),
+    );
+    let calls = vec![
+        call("read_file", r#"{"path":"Cargo.toml","limit":1}"#),
+        call("bash", r#"{"command":"echo one"}"#),
+        call("bash", r#"{"command":"git status"}"#),
+        call("bash", r#"{"command":"git status --short && git status"}"#),
+        call("bash", r#"{"command":"git status; rm -rf target"}"#),
+    ];
+
+    let outputs = run_approved(&calls, &approvals).await;
+
+    assert!(outputs[0].contains("[package]"));
+    assert_eq!(
+        outputs[1],
+        "Error: the user denied this tool call: use printf"
+    );
+    assert!(!outputs[2].starts_with("Error"), "{}", outputs[2]);
+    assert!(!outputs[3].starts_with("Error"), "{}", outputs[3]);
+    assert_eq!(outputs[4], "Error: the user denied this tool call");
+    assert_eq!(
+        *approver.asked.lock().unwrap(),
+        ["$ echo one", "$ git status", "$ git status; rm -rf target"]
+    );
+}
+
+#[tokio::test]
+async fn execute_all_denies_without_an_approver() {
+    let approvals = Approvals::new(
+        Permissions::default(),
+        ApprovalMode::Ask,
+        Workspace::default(),
+        None,
+    );
+    let calls = vec![call("ls", "{}")];
+
+    let outputs = run_approved(&calls, &approvals).await;
+
+    assert!(outputs[0].starts_with("Error: ls needs the user's approval"));
+}
+
+#[tokio::test]
+async fn execute_all_asks_before_leaving_the_workspace() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let workspace = Workspace::new([manifest_dir.join("src")], OutsideAccess::Ask);
+    let approver = Arc::new(Scripted {
+        answers: Mutex::new(vec![Approval::Approve, Approval::Deny(None)]),
+        asked: Mutex::new(Vec::new()),
+    });
+    let approvals = Approvals::new(
+        Permissions::default(),
+        ApprovalMode::Auto,
+        workspace.clone(),
+        Some(approver.clone()),
+    );
+    let manifest = manifest_dir.join("src/../Cargo.toml");
+    let arguments = serde_json::json!({ "path": manifest, "limit": 1 }).to_string();
+    let calls = vec![
+        call("read_file", &arguments),
+        call("read_file", &arguments),
+        call("ls", r#"{"path":"src"}"#),
+    ];
+    let ctx = ToolContext {
+        workspace,
+        ..ToolContext::default()
+    };
+
+    let outputs = execute_all(
+        &calls,
+        &ToolRegistry::builtin(),
+        &approvals,
+        &Redactor::default(),
+        &ctx,
+        8,
+        &mut Recorder::default(),
+    )
+    .await
+    .expect("calls should run");
+
+    assert_eq!(outputs[0], "[package]");
+    assert_eq!(outputs[1], "Error: the user denied this tool call");
+    assert!(outputs[2].contains("main.rs"), "{}", outputs[2]);
+    let asked = approver.asked.lock().unwrap();
+    assert_eq!(asked.len(), 2);
+    assert!(
+        asked[0].starts_with(&format!(
+            "{} resolves to {}, outside the workspace:\n",
+            manifest.display(),
+            manifest_dir.join("Cargo.toml").display()
+        )),
+        "{}",
+        asked[0]
+    );
+}
+
+#[tokio::test]
+async fn edits_of_redacted_text_write_the_real_values() {
+    let integrity = "sha512-z4PhNX7vuL3xVChQ1m2AB9Yg5BULVxnrAN5JtS+ELdYIJ5nmuJQ8x9RwuqYzQ1Jc+P6sDPBCKHc/CJmS9Thxpg==";
+    let path = std::env::temp_dir().join(format!("ox-lockfile-{:016x}.json", fastrand::u64(..)));
+    let original = format!("{{\n  \"version\": \"1.0.0\",\n  \"integrity\": \"{integrity}\"\n}}\n");
+    std::fs::write(&path, &original).expect("write file");
+    let redactor = Redactor::default();
+    let approvals = Approvals::new(
+        Permissions::default(),
+        ApprovalMode::Auto,
+        Workspace::default(),
+        None,
+    );
+    let run = |calls: Vec<ToolCall>| {
+        let (redactor, approvals) = (&redactor, &approvals);
+        async move {
+            execute_all(
+                &calls,
+                &ToolRegistry::builtin(),
+                approvals,
+                redactor,
+                &ToolContext::default(),
+                8,
+                &mut Recorder::default(),
+            )
+            .await
+            .expect("calls should run")
+        }
+    };
+
+    let read = run(vec![call(
+        "read_file",
+        &serde_json::json!({ "path": path }).to_string(),
+    )])
+    .await;
+    assert!(!read[0].contains(integrity), "{}", read[0]);
+    let start = read[0].find("{\n").expect("file content");
+    let seen = &read[0][start..];
+    let edit = serde_json::json!({
+        "path": path,
+        "old_text": seen.trim_end(),
+        "new_text": seen.trim_end().replace("1.0.0", "1.0.1"),
+    });
+    let edited = run(vec![call("edit", &edit.to_string())]).await;
+
+    assert!(!edited[0].starts_with("Error"), "{}", edited[0]);
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        original.replace("1.0.0", "1.0.1")
+    );
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,153 @@
+use super::{RetryPolicy, send_model_request};
+use crate::agent::AgentEvent;
+use crate::agent::approval::{ApprovalMode, Approvals};
+use crate::app_context::AppContext;
+use crate::auth::AuthConfig;
+use crate::config::Config;
+use crate::permissions::Permissions;
+use crate::provider::{Reasoning, ResponsesProvider};
+use crate::redact::Redactor;
+use crate::sandbox::Sandbox;
+use crate::test_support::{Recorder, spawn_server};
+use crate::tools::{CommandEnv, OutputLimits, ToolRegistry, Workspace};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// An unsigned JWT that is valid for an hour; only its claims are read.
+fn token(name: &str) -> String {
+    let encode = |json: serde_json::Value| URL_SAFE_NO_PAD.encode(json.to_string());
+    let exp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    format!(
+        "{}.{}.sig",
+        encode(serde_json::json!({ "alg": "HS256" })),
+        encode(serde_json::json!({ "exp": exp, "name": name }))
+    )
+}
+
+/// What the stub server saw.
+#[derive(Default)]
+struct Seen {
+    authorizations: Vec<String>,
+    refreshes: usize,
+}
+
+/// A model API answering with `statuses` in turn, plus the token endpoint
+/// that hands out `refreshed`.
+async fn spawn_api(statuses: Vec<u16>, refreshed: String, seen: Arc<Mutex<Seen>>) -> String {
+    let mut statuses = statuses.into_iter();
+    spawn_server("application/json", move |request| {
+        if request.head.starts_with("POST /oauth/token") {
+            seen.lock().unwrap().refreshes += 1;
+            let body =
+                serde_json::json!({ "access_token": refreshed, "refresh_token": "refresh-2" });
+            return (200, body.to_string());
+        }
+        let authorization = request
+            .head
+            .lines()
+            .find_map(|line| line.strip_prefix("authorization: "))
+            .unwrap_or_default()
+            .to_string();
+        seen.lock().unwrap().authorizations.push(authorization);
+        (statuses.next().unwrap_or(500), "{}".to_string())
+    })
+    .await
+}
+
+/// A subscription-mode context whose stored access token is `rejected`.
+fn app(url: &str, rejected: &str) -> (AppContext, PathBuf) {
+    let home = std::env::temp_dir().join(format!("ox-api-{:016x}", fastrand::u64(..)));
+    std::fs::create_dir_all(&home).unwrap();
+    let auth_file = serde_json::json!({
+        "tokens": { "access_token": rejected, "refresh_token": "refresh-1", "account_id": "acct" }
+    });
+    std::fs::write(home.join("auth.json"), auth_file.to_string()).unwrap();
+    let config = Config::from_layers(
+        Vec::new(),
+        |_| None,
+        &[
+            ("auth.mode".to_string(), "subscription".to_string()),
+            ("auth.codex_home".to_string(), home.display().to_string()),
+            ("auth.issuer".to_string(), url.to_string()),
+            ("base_url".to_string(), url.to_string()),
+        ],
+    )
+    .unwrap();
+    let app = AppContext {
+        client: reqwest::Client::new(),
+        auth: AuthConfig::from_config(&config).unwrap(),
+        provider: Box::new(ResponsesProvider),
+        tools: ToolRegistry::default(),
+        approvals: Approvals::new(
+            Permissions::default(),
+            ApprovalMode::Auto,
+            Workspace::default(),
+            None,
+        ),
+        redactor: Redactor::default(),
+        tool_defs: Vec::new(),
+        instructions: String::new(),
+        retry: RetryPolicy::from_config(&config),
+        reasoning: Reasoning::default(),
+        max_tool_calls: 1,
+        max_parallel_tools: 1,
+        output_limits: OutputLimits::default(),
+        sandbox: Sandbox::default(),
+        command_env: CommandEnv::default(),
+        workspace: Workspace::default(),
+    };
+    (app, home)
+}
+
+#[tokio::test]
+async fn a_rejected_token_is_refreshed_and_the_request_replayed_once() {
+    let (rejected, refreshed) = (token("rejected"), token("refreshed"));
+    let seen = Arc::new(Mutex::new(Seen::default()));
+    let url = spawn_api(vec![401, 200], refreshed.clone(), Arc::clone(&seen)).await;
+    let (app, home) = app(&url, &rejected);
+
+    let mut recorder = Recorder::default();
+    let response = send_model_request(&app, &[], &mut recorder)
+        .await
+        .expect("replay should succeed");
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        recorder.0,
+        vec![AgentEvent::Warning(
+            "access token rejected (401); refreshing it and retrying once".to_string()
+        )]
+    );
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.refreshes, 1);
+    assert_eq!(
+        seen.authorizations,
+        vec![format!("Bearer {rejected}"), format!("Bearer {refreshed}")]
+    );
+    let stored = std::fs::read_to_string(home.join("auth.json")).unwrap();
+    assert!(stored.contains(&refreshed));
+    let _ = std::fs::remove_dir_all(home);
+}
+
+#[tokio::test]
+async fn a_second_rejection_is_returned_as_an_error() {
+    let seen = Arc::new(Mutex::new(Seen::default()));
+    let url = spawn_api(vec![401, 401, 200], token("refreshed"), Arc::clone(&seen)).await;
+    let (app, home) = app(&url, &token("rejected"));
+
+    let error = send_model_request(&app, &[], &mut Recorder::default())
+        .await
+        .expect_err("a second 401 should fail");
+
+    assert!(error.to_string().contains("401"), "{error:#}");
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.refreshes, 1);
+    assert_eq!(seen.authorizations.len(), 2);
+    let _ = std::fs::remove_dir_all(home);
+}
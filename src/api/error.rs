@@ -0,0 +1,59 @@
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+const MAX_RAW_BODY_CHARS: usize = 500;
+
+#[derive(Deserialize)]
+struct ErrorEnvelope {
+    #[serde(default)]
+    error: Option<ErrorBody>,
+    #[serde(default)]
+    detail: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(rename = "type", default)]
+    error_type: Option<String>,
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+}
+
+/// Turn an error response into a one-line message, preferring the API's own
+/// `error.message` (or `detail`) over the raw body.
+pub(super) fn describe(status: StatusCode, body: &str) -> String {
+    parse_message(body).map_or_else(
+        || {
+            let raw = body.trim();
+            if raw.is_empty() {
+                format!("API request failed ({status})")
+            } else {
+                let preview: String = raw.chars().take(MAX_RAW_BODY_CHARS).collect();
+                format!("API request failed ({status}): {preview}")
+            }
+        },
+        |message| format!("API request failed ({status}): {message}"),
+    )
+}
+
+fn parse_message(body: &str) -> Option<String> {
+    let envelope: ErrorEnvelope = serde_json::from_str(body).ok()?;
+    if let Some(error) = envelope.error {
+        let message = error.message.filter(|message| !message.is_empty())?;
+        let kind = error
+            .code
+            .and_then(|code| match code {
+                serde_json::Value::String(code) => Some(code),
+                serde_json::Value::Null => None,
+                other => Some(other.to_string()),
+            })
+            .or(error.error_type);
+        return Some(kind.map_or_else(|| message.clone(), |kind| format!("{message} [{kind}]")));
+    }
+    match envelope.detail? {
+        serde_json::Value::String(detail) => Some(detail),
+        other => Some(other.to_string()),
+    }
+}
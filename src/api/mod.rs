@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod api_tests;
+mod error;
+mod retry;
+#[cfg(test)]
+mod retry_tests;
+
+use crate::agent::{AgentEvent, EventSink};
+use crate::app_context::AppContext;
+use crate::{provider, session};
+use anyhow::{Context, Result, anyhow};
+use reqwest::Response;
+
+pub use retry::RetryPolicy;
+
+/// Send the model request for `history` through the configured provider,
+/// retrying transient failures and refreshing a rejected token once.
+pub async fn send_model_request(
+    app: &AppContext,
+    history: &[serde_json::Value],
+    sink: &mut dyn EventSink,
+) -> Result<Response> {
+    let policy = &app.retry;
+    let api = app.provider.display_name();
+    let mut attempt = 1;
+    let mut refreshed_auth = false;
+
+    loop {
+        let (reason, server_hint) = match send_request(app, history).await? {
+            Ok(response) if response.status() == reqwest::StatusCode::OK => return Ok(response),
+            Ok(response)
+                if response.status() == reqwest::StatusCode::UNAUTHORIZED
+                    && app.auth.can_refresh()
+                    && !refreshed_auth =>
+            {
+                sink.on_event(&AgentEvent::Warning(
+                    "access token rejected (401); refreshing it and retrying once".to_string(),
+                ));
+                app.auth
+                    .force_refresh(&app.client)
+                    .await
+                    .context("failed to refresh subscription token after 401")?;
+                refreshed_auth = true;
+                continue;
+            }
+            Ok(response) => {
+                let status = response.status();
+                let server_hint = retry::server_delay(response.headers());
+                let body = response.text().await.unwrap_or_default();
+                let message = error::describe(status, &body);
+                if !retry::is_retryable(status) || attempt >= policy.max_attempts {
+                    return Err(anyhow!(message));
+                }
+                (format!("{api} returned {status}"), server_hint)
+            }
+            Err(err) => {
+                if !retry::is_retryable_send_error(&err) || attempt >= policy.max_attempts {
+                    return Err(err).with_context(|| format!("failed to send request to {api}"));
+                }
+                (format!("Request to {api} failed ({err})"), None)
+            }
+        };
+
+        let delay = policy.delay(attempt, server_hint);
+        attempt += 1;
+        retry::countdown(delay, &reason, attempt, policy.max_attempts, sink).await;
+    }
+}
+
+/// Wait before retry number `attempt` (1-based) of a request that failed
+/// for `reason` after it was accepted, such as an error event mid-stream.
+pub async fn backoff(policy: &RetryPolicy, attempt: u32, reason: &str, sink: &mut dyn EventSink) {
+    let delay = policy.delay(attempt, None);
+    retry::countdown(delay, reason, attempt + 1, policy.max_attempts, sink).await;
+}
+
+async fn send_request(
+    app: &AppContext,
+    history: &[serde_json::Value],
+) -> Result<reqwest::Result<Response>> {
+    let AppContext {
+        client,
+        auth,
+        provider,
+        tool_defs,
+        instructions,
+        reasoning,
+        ..
+    } = app;
+    let mut headers = provider.request_headers();
+    headers.extend(auth.build_headers(client).await?);
+    let history: Vec<serde_json::Value> = history
+        .iter()
+        .filter(|entry| !session::is_diagnostic(entry))
+        .cloned()
+        .collect();
+    let body = provider.build_request(&provider::Request {
+        model: auth.model(),
+        instructions,
+        history: &history,
+        tools: tool_defs,
+        reasoning,
+    });
+    let request = client
+        .post(provider.endpoint(auth))
+        .headers(headers)
+        .json(&body);
+
+    Ok(request.send().await)
+}
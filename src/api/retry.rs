@@ -0,0 +1,159 @@
+use crate::agent::{AgentEvent, EventSink};
+use crate::config::Config;
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::time::{Duration, SystemTime};
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub(super) max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        let millis = |key| Duration::from_millis(config.usize(key) as u64);
+        Self {
+            max_attempts: u32::try_from(config.usize("retry.max_attempts")).unwrap_or(u32::MAX),
+            base_delay: millis("retry.base_delay_ms"),
+            max_delay: millis("retry.max_delay_ms"),
+        }
+    }
+
+    pub const fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Delay before retry number `attempt` (1-based). A server-provided hint
+    /// wins; otherwise exponential backoff with full jitter.
+    pub(super) fn delay(&self, attempt: u32, server_hint: Option<Duration>) -> Duration {
+        if let Some(hint) = server_hint {
+            return hint.min(self.max_delay);
+        }
+        let exponent = attempt.saturating_sub(1).min(16);
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        ceiling.mul_f64(fastrand::f64().mul_add(0.5, 0.5))
+    }
+}
+
+/// Anthropic's "overloaded" status.
+const OVERLOADED: u16 = 529;
+
+pub(super) fn is_retryable(status: StatusCode) -> bool {
+    status.as_u16() == OVERLOADED
+        || matches!(
+            status,
+            StatusCode::REQUEST_TIMEOUT
+                | StatusCode::CONFLICT
+                | StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+}
+
+pub(super) fn is_retryable_send_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// How long the server asked us to wait, from `retry-after-ms`,
+/// `retry-after` (seconds or HTTP date) or the `x-ratelimit-reset-*` headers.
+pub(super) fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(ms) = header_str(headers, "retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(ms / 1000.0).ok();
+    }
+    if let Some(value) = header_str(headers, "retry-after") {
+        if let Ok(seconds) = value.parse::<f64>() {
+            return Duration::try_from_secs_f64(seconds).ok();
+        }
+        if let Ok(date) = httpdate::parse_http_date(value) {
+            return Some(
+                date.duration_since(SystemTime::now())
+                    .unwrap_or(Duration::ZERO),
+            );
+        }
+    }
+    rate_limit_reset(headers)
+}
+
+/// Pick the reset time of an exhausted rate-limit bucket, or the longest
+/// reset when no bucket reports zero remaining.
+fn rate_limit_reset(headers: &HeaderMap) -> Option<Duration> {
+    let mut exhausted = None;
+    let mut longest = None;
+    for bucket in ["requests", "tokens"] {
+        let Some(reset) =
+            header_str(headers, &format!("x-ratelimit-reset-{bucket}")).and_then(parse_go_duration)
+        else {
+            continue;
+        };
+        let remaining = header_str(headers, &format!("x-ratelimit-remaining-{bucket}"));
+        if remaining == Some("0") {
+            exhausted = exhausted.max(Some(reset));
+        }
+        longest = longest.max(Some(reset));
+    }
+    exhausted.or(longest)
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok().map(str::trim)
+}
+
+/// Parse durations such as `1s`, `6m0s`, `20ms` or `1h2m3.5s`.
+pub(super) fn parse_go_duration(raw: &str) -> Option<Duration> {
+    let mut total = 0.0_f64;
+    let mut rest = raw.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            "us" | "µs" => 0.000_001,
+            "ns" => 0.000_000_001,
+            _ => return None,
+        };
+        total += value * scale;
+        rest = &rest[unit_len..];
+    }
+    Duration::try_from_secs_f64(total).ok()
+}
+
+/// Sleep for `delay`, reporting the time left to `sink` every second.
+pub(super) async fn countdown(
+    delay: Duration,
+    reason: &str,
+    next_attempt: u32,
+    max_attempts: u32,
+    sink: &mut dyn EventSink,
+) {
+    let mut remaining = delay;
+    while !remaining.is_zero() {
+        sink.on_event(&AgentEvent::Retrying {
+            reason: reason.to_string(),
+            attempt: next_attempt,
+            max_attempts,
+            remaining,
+        });
+        let step = remaining.min(Duration::from_secs(1));
+        tokio::time::sleep(step).await;
+        remaining = remaining.saturating_sub(step);
+    }
+}
@@ -0,0 +1,66 @@
+use super::error::describe;
+use super::retry::{RetryPolicy, parse_go_duration, server_delay};
+use crate::config::Config;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::time::Duration;
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in pairs {
+        map.insert(*name, HeaderValue::from_str(value).expect("valid header"));
+    }
+    map
+}
+
+#[test]
+fn parse_go_duration_handles_compound_units() {
+    assert_eq!(parse_go_duration("20ms"), Some(Duration::from_millis(20)));
+    assert_eq!(parse_go_duration("6m0s"), Some(Duration::from_mins(6)));
+    assert_eq!(parse_go_duration("1.5s"), Some(Duration::from_millis(1500)));
+    assert_eq!(parse_go_duration("soon"), None);
+}
+
+#[test]
+fn server_delay_prefers_retry_after() {
+    let map = headers(&[("retry-after", "7"), ("x-ratelimit-reset-requests", "1s")]);
+    assert_eq!(server_delay(&map), Some(Duration::from_secs(7)));
+}
+
+#[test]
+fn server_delay_uses_exhausted_rate_limit_bucket() {
+    let map = headers(&[
+        ("x-ratelimit-remaining-requests", "10"),
+        ("x-ratelimit-reset-requests", "30s"),
+        ("x-ratelimit-remaining-tokens", "0"),
+        ("x-ratelimit-reset-tokens", "2s"),
+    ]);
+    assert_eq!(server_delay(&map), Some(Duration::from_secs(2)));
+}
+
+#[test]
+fn delay_is_capped_and_jittered() {
+    let policy = RetryPolicy::from_config(&Config::defaults());
+    for attempt in 1..10 {
+        assert!(policy.delay(attempt, None) <= Duration::from_mins(1));
+    }
+    let first = policy.delay(1, None);
+    assert!(first >= Duration::from_millis(500) && first <= Duration::from_secs(1));
+    assert_eq!(
+        policy.delay(1, Some(Duration::from_mins(10))),
+        Duration::from_mins(1)
+    );
+}
+
+#[test]
+fn describe_extracts_api_error_message() {
+    let body = r#"{"error":{"message":"Invalid API key","type":"invalid_request_error","code":"invalid_api_key"}}"#;
+    assert_eq!(
+        describe(StatusCode::UNAUTHORIZED, body),
+        "API request failed (401 Unauthorized): Invalid API key [invalid_api_key]"
+    );
+    assert_eq!(
+        describe(StatusCode::BAD_REQUEST, r#"{"detail":"Bad model"}"#),
+        "API request failed (400 Bad Request): Bad model"
+    );
+}
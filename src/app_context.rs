@@ -1,19 +1,22 @@
-use crate::{auth, prompt, tools};
+use crate::agent::approval::Approvals;
+use crate::{api, auth, provider, redact, sandbox, tools};
 
+/// Everything a turn needs besides the history. Built by `AgentBuilder`.
 pub struct AppContext {
     pub client: reqwest::Client,
     pub auth: auth::AuthConfig,
+    pub provider: Box<dyn provider::Provider>,
+    pub tools: tools::ToolRegistry,
+    pub approvals: Approvals,
+    pub redactor: redact::Redactor,
     pub tool_defs: Vec<serde_json::Value>,
     pub instructions: String,
-}
-
-impl AppContext {
-    pub fn new() -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            auth: auth::AuthConfig::from_env(),
-            tool_defs: tools::definitions(),
-            instructions: prompt::build(),
-        }
-    }
+    pub retry: api::RetryPolicy,
+    pub reasoning: provider::Reasoning,
+    pub max_tool_calls: usize,
+    pub max_parallel_tools: usize,
+    pub output_limits: tools::OutputLimits,
+    pub sandbox: sandbox::Sandbox,
+    pub command_env: tools::CommandEnv,
+    pub workspace: tools::Workspace,
 }
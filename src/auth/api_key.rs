@@ -0,0 +1,30 @@
+use anyhow::{Context, Result, bail};
+use std::process::{Command, Stdio};
+
+/// Run the configured `api_key_command` (e.g. a secret manager CLI) and
+/// return its trimmed stdout.
+pub(super) async fn run_api_key_command(command: String) -> Result<String> {
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+    })
+    .await
+    .context("API key command task panicked")?
+    .context("failed to run api_key_command")?;
+
+    if !output.status.success() {
+        bail!("api_key_command failed ({})", output.status);
+    }
+    let key = String::from_utf8(output.stdout)
+        .context("api_key_command printed non-UTF-8 output")?
+        .trim()
+        .to_string();
+    if key.is_empty() {
+        bail!("api_key_command printed an empty key");
+    }
+    Ok(key)
+}
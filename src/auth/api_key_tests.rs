@@ -0,0 +1,49 @@
+use super::AuthConfig;
+use super::api_key::run_api_key_command;
+use crate::config::Config;
+use reqwest::header::AUTHORIZATION;
+
+#[tokio::test]
+async fn api_key_command_output_is_trimmed() {
+    let key = run_api_key_command("printf '  sk-test-key \\n\\n'".to_string()).await;
+
+    assert_eq!(key.expect("command should succeed"), "sk-test-key");
+}
+
+#[tokio::test]
+async fn api_key_command_failures_are_errors() {
+    let error = run_api_key_command("exit 3".to_string())
+        .await
+        .expect_err("non-zero exit should fail");
+    assert_eq!(error.to_string(), "api_key_command failed (exit status: 3)");
+
+    let error = run_api_key_command("true".to_string())
+        .await
+        .expect_err("empty output should fail");
+    assert_eq!(error.to_string(), "api_key_command printed an empty key");
+}
+
+#[tokio::test]
+async fn api_key_command_runs_once_per_config() {
+    let counter = std::env::temp_dir().join(format!("ox-api-key-{:016x}", fastrand::u64(..)));
+    let command = format!("echo run >> '{}'; echo sk-cached", counter.display());
+    let config = Config::from_layers(
+        Vec::new(),
+        |_| None,
+        &[
+            ("auth.mode".to_string(), "api".to_string()),
+            ("api_key_command".to_string(), command),
+        ],
+    )
+    .expect("valid config");
+    let auth = AuthConfig::from_config(&config).expect("valid auth config");
+    let client = reqwest::Client::new();
+
+    for _ in 0..2 {
+        let headers = auth.build_headers(&client).await.expect("headers");
+        assert_eq!(headers[AUTHORIZATION], "Bearer sk-cached");
+    }
+    let runs = std::fs::read_to_string(&counter).expect("command ran");
+    assert_eq!(runs.lines().count(), 1);
+    let _ = std::fs::remove_file(counter);
+}
@@ -0,0 +1,24 @@
+use super::SubscriptionAuth;
+use super::subscriptions;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Subscription credentials kept in memory until their access token expires.
+/// The mutex also serializes refreshes within one process.
+pub(super) type SubscriptionCache = Arc<Mutex<Option<CachedAuth>>>;
+
+pub(super) struct CachedAuth {
+    pub(super) auth: SubscriptionAuth,
+    expires_at: Option<i64>,
+}
+
+impl CachedAuth {
+    pub(super) fn new(auth: SubscriptionAuth) -> Self {
+        let expires_at = subscriptions::access_token_expiry(&auth.access_token);
+        Self { auth, expires_at }
+    }
+
+    pub(super) fn is_expired(&self) -> bool {
+        self.expires_at.is_none_or(subscriptions::is_expiry_passed)
+    }
+}
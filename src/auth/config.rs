@@ -1,29 +1,61 @@
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/responses";
-const SUBSCRIPTION_API_URL: &str = "https://chatgpt.com/backend-api/codex/responses";
+use super::cache::SubscriptionCache;
+use super::store::AuthStore;
+use crate::config::Config;
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const SUBSCRIPTION_BASE_URL: &str = "https://chatgpt.com/backend-api/codex";
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
 
 #[derive(Clone, Copy)]
 pub(super) enum AuthMode {
     ApiKey,
     Subscription,
+    Anthropic,
+    Azure,
+    None,
 }
 
 #[derive(Clone, Copy)]
 struct AuthProfile {
-    url: &'static str,
+    base_url: &'static str,
     default_model: &'static str,
 }
 
+/// An Azure resource hosting model deployments. Requests go to deployment-scoped paths and
+/// carry an `api-version` query parameter.
+#[derive(Clone)]
+pub(super) struct AzureTarget {
+    endpoint: String,
+    deployment: String,
+    api_version: String,
+}
+
 #[derive(Clone)]
 pub struct AuthConfig {
     mode: AuthMode,
-    url: &'static str,
+    base_url: String,
     model: String,
+    extra_headers: HeaderMap,
+    azure: Option<AzureTarget>,
+    api_key_command: Option<String>,
+    api_key_cache: Arc<OnceCell<String>>,
+    subscription_cache: SubscriptionCache,
+    store: AuthStore,
+    issuer: String,
 }
 
 impl AuthMode {
-    fn from_env_value(raw: Option<&str>) -> Self {
-        match raw.unwrap_or("api").to_ascii_lowercase().as_str() {
+    fn parse(raw: &str) -> Self {
+        match raw.to_ascii_lowercase().as_str() {
             "subscription" => Self::Subscription,
+            "anthropic" => Self::Anthropic,
+            "azure" => Self::Azure,
+            "none" => Self::None,
             _ => Self::ApiKey,
         }
     }
@@ -32,21 +64,74 @@ impl AuthMode {
         match self {
             Self::ApiKey => "api",
             Self::Subscription => "subscription",
+            Self::Anthropic => "anthropic",
+            Self::Azure => "azure",
+            Self::None => "none",
+        }
+    }
+}
+
+impl AzureTarget {
+    fn from_config(config: &Config) -> Result<Self> {
+        let endpoint = config
+            .string("azure.endpoint")
+            .context("azure auth mode requires azure.endpoint (AZURE_OPENAI_ENDPOINT)")?;
+        let deployment = config
+            .string("azure.deployment")
+            .context("azure auth mode requires azure.deployment (AZURE_OPENAI_DEPLOYMENT)")?;
+        Ok(Self {
+            endpoint: trimmed_url(endpoint),
+            deployment: deployment.to_string(),
+            api_version: config
+                .string("azure.api_version")
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    /// The Responses API lives at the resource level; every other API is
+    /// scoped to the deployment.
+    fn url(&self, path: &str) -> String {
+        let Self {
+            endpoint,
+            deployment,
+            api_version,
+        } = self;
+        if path == "responses" {
+            format!("{endpoint}/openai/responses?api-version={api_version}")
+        } else {
+            format!("{endpoint}/openai/deployments/{deployment}/{path}?api-version={api_version}")
         }
     }
 }
 
 impl AuthConfig {
-    pub fn from_env() -> Self {
-        let mode = AuthMode::from_env_value(std::env::var("AUTH_MODE").ok().as_deref());
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mode = AuthMode::parse(config.string("auth.mode").unwrap_or_default());
         let profile = auth_profile(mode);
-        let model = std::env::var("OPENAI_MODEL")
-            .unwrap_or_else(|_| profile.default_model.to_string());
-        Self {
+        let azure = matches!(mode, AuthMode::Azure)
+            .then(|| AzureTarget::from_config(config))
+            .transpose()?;
+        let default_model = azure
+            .as_ref()
+            .map_or(profile.default_model, |azure| azure.deployment.as_str());
+        let model = config.string("model").unwrap_or(default_model).to_string();
+        let base_url = config
+            .string("base_url")
+            .map_or_else(|| profile.base_url.to_string(), trimmed_url);
+
+        Ok(Self {
             mode,
-            url: profile.url,
+            base_url,
             model,
-        }
+            extra_headers: extra_headers(config),
+            azure,
+            api_key_command: config.string("api_key_command").map(str::to_string),
+            api_key_cache: Arc::default(),
+            subscription_cache: SubscriptionCache::default(),
+            store: AuthStore::new(config.string("auth.codex_home").map(PathBuf::from)),
+            issuer: trimmed_url(config.string("auth.issuer").unwrap_or_default()),
+        })
     }
 
     pub const fn mode_name(&self) -> &'static str {
@@ -57,24 +142,84 @@ impl AuthConfig {
         self.model.as_str()
     }
 
-    pub const fn endpoint(&self) -> &'static str {
-        self.url
+    /// URL for an API path such as `responses` or `chat/completions`,
+    /// resolved against the configured base URL or Azure resource.
+    pub fn api_url(&self, path: &str) -> String {
+        self.azure.as_ref().map_or_else(
+            || format!("{}/{path}", self.base_url),
+            |azure| azure.url(path),
+        )
+    }
+
+    pub const fn is_anthropic(&self) -> bool {
+        matches!(self.mode, AuthMode::Anthropic)
     }
 
     pub(super) const fn mode(&self) -> AuthMode {
         self.mode
     }
+
+    pub(super) const fn extra_headers(&self) -> &HeaderMap {
+        &self.extra_headers
+    }
+
+    pub(super) fn api_key_command(&self) -> Option<&str> {
+        self.api_key_command.as_deref()
+    }
+
+    pub(super) fn api_key_cache(&self) -> &OnceCell<String> {
+        &self.api_key_cache
+    }
+
+    pub(super) const fn subscription_cache(&self) -> &SubscriptionCache {
+        &self.subscription_cache
+    }
+
+    pub(super) const fn store(&self) -> &AuthStore {
+        &self.store
+    }
+
+    /// Base URL of the OAuth server used for login and refresh.
+    pub(super) const fn issuer(&self) -> &str {
+        self.issuer.as_str()
+    }
+}
+
+fn trimmed_url(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// Headers from the `extra_headers` table, e.g. `{"x-team": "infra"}`.
+/// Invalid entries are skipped with a warning.
+fn extra_headers(config: &Config) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in config.table("extra_headers") {
+        let parsed = HeaderName::try_from(name.as_str())
+            .ok()
+            .zip(HeaderValue::from_str(&value).ok());
+        match parsed {
+            Some((name, value)) => {
+                headers.insert(name, value);
+            }
+            None => eprintln!("Warning: ignoring invalid extra header '{name}'"),
+        }
+    }
+    headers
 }
 
 const fn auth_profile(mode: AuthMode) -> AuthProfile {
     match mode {
-        AuthMode::ApiKey => AuthProfile {
-            url: OPENAI_API_URL,
+        AuthMode::ApiKey | AuthMode::Azure | AuthMode::None => AuthProfile {
+            base_url: OPENAI_BASE_URL,
             default_model: "gpt-4.1-mini",
         },
         AuthMode::Subscription => AuthProfile {
-            url: SUBSCRIPTION_API_URL,
+            base_url: SUBSCRIPTION_BASE_URL,
             default_model: "gpt-5.3-codex",
         },
+        AuthMode::Anthropic => AuthProfile {
+            base_url: ANTHROPIC_BASE_URL,
+            default_model: "claude-sonnet-4-5",
+        },
     }
 }
@@ -0,0 +1,72 @@
+use super::AuthConfig;
+use crate::config::Config;
+
+fn auth(overrides: &[(&str, &str)]) -> anyhow::Result<AuthConfig> {
+    let overrides: Vec<(String, String)> = overrides
+        .iter()
+        .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+        .collect();
+    let config = Config::from_layers(Vec::new(), |_| None, &overrides).expect("valid config");
+    AuthConfig::from_config(&config)
+}
+
+#[test]
+fn azure_urls_are_scoped_to_the_deployment_except_responses() {
+    let auth = auth(&[
+        ("auth.mode", "azure"),
+        ("azure.endpoint", "https://team.openai.azure.com/"),
+        ("azure.deployment", "gpt-prod"),
+        ("azure.api_version", "2025-04-01-preview"),
+    ])
+    .expect("valid azure config");
+
+    assert_eq!(auth.model(), "gpt-prod");
+    assert_eq!(
+        auth.api_url("chat/completions"),
+        "https://team.openai.azure.com/openai/deployments/gpt-prod/chat/completions?api-version=2025-04-01-preview"
+    );
+    assert_eq!(
+        auth.api_url("responses"),
+        "https://team.openai.azure.com/openai/responses?api-version=2025-04-01-preview"
+    );
+}
+
+#[test]
+fn azure_without_an_endpoint_is_a_config_error() {
+    let error = auth(&[("auth.mode", "azure"), ("azure.deployment", "gpt-prod")])
+        .err()
+        .expect("missing endpoint should fail");
+
+    assert_eq!(
+        error.to_string(),
+        "azure auth mode requires azure.endpoint (AZURE_OPENAI_ENDPOINT)"
+    );
+}
+
+#[test]
+fn azure_without_a_deployment_is_a_config_error() {
+    let error = auth(&[
+        ("auth.mode", "azure"),
+        ("azure.endpoint", "https://team.openai.azure.com"),
+    ])
+    .err()
+    .expect("missing deployment should fail");
+
+    assert_eq!(
+        error.to_string(),
+        "azure auth mode requires azure.deployment (AZURE_OPENAI_DEPLOYMENT)"
+    );
+}
+
+#[test]
+fn extra_headers_skip_invalid_names_and_values() {
+    let auth = auth(&[(
+        "extra_headers",
+        r#"{"x-team": "infra", "bad name": "x", "x-multi": "a\nb"}"#,
+    )])
+    .expect("valid config");
+
+    let headers = auth.extra_headers();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers["x-team"], "infra");
+}
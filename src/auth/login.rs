@@ -0,0 +1,285 @@
+use super::subscriptions::{self, CLIENT_ID, RefreshedSubscriptionTokens};
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const CALLBACK_PORT: u16 = 1455;
+const CALLBACK_PATH: &str = "/auth/callback";
+const SCOPES: &str = "openid profile email offline_access";
+const BROWSER_LOGIN_TIMEOUT: Duration = Duration::from_mins(5);
+const DEVICE_LOGIN_TIMEOUT: Duration = Duration::from_mins(15);
+const MAX_CALLBACK_REQUEST_BYTES: usize = 16 * 1024;
+
+struct Pkce {
+    verifier: String,
+    challenge: String,
+}
+
+impl Pkce {
+    fn generate() -> Result<Self> {
+        let verifier = random_token(64)?;
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Ok(Self {
+            verifier,
+            challenge,
+        })
+    }
+}
+
+fn random_token(bytes: usize) -> Result<String> {
+    let mut buf = vec![0_u8; bytes];
+    getrandom::fill(&mut buf).map_err(|e| anyhow!("failed to generate random bytes: {e}"))?;
+    Ok(URL_SAFE_NO_PAD.encode(buf))
+}
+
+/// Bind the localhost listener that receives the OAuth redirect. Done before
+/// anything is shown to the user so a busy port can fall back to device login.
+pub(super) async fn bind_callback_listener() -> Result<TcpListener> {
+    TcpListener::bind(("127.0.0.1", CALLBACK_PORT))
+        .await
+        .with_context(|| format!("failed to listen on localhost:{CALLBACK_PORT}"))
+}
+
+/// Browser login: authorization code + PKCE, with the redirect caught by a
+/// one-shot listener on localhost.
+pub(super) async fn browser_login(
+    client: &reqwest::Client,
+    issuer: &str,
+    listener: TcpListener,
+) -> Result<RefreshedSubscriptionTokens> {
+    let redirect_uri = format!("http://localhost:{CALLBACK_PORT}{CALLBACK_PATH}");
+    let pkce = Pkce::generate()?;
+    let state = random_token(32)?;
+
+    let url = reqwest::Url::parse_with_params(
+        &format!("{issuer}/oauth/authorize"),
+        &[
+            ("response_type", "code"),
+            ("client_id", CLIENT_ID),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("scope", SCOPES),
+            ("code_challenge", pkce.challenge.as_str()),
+            ("code_challenge_method", "S256"),
+            ("id_token_add_organizations", "true"),
+            ("codex_cli_simplified_flow", "true"),
+            ("state", state.as_str()),
+            ("originator", "ox"),
+        ],
+    )
+    .context("failed to build authorization URL")?;
+
+    eprintln!("Open this URL in your browser to sign in:\n\n  {url}\n");
+    open_browser(url.as_str());
+
+    let code = tokio::time::timeout(BROWSER_LOGIN_TIMEOUT, wait_for_callback(&listener, &state))
+        .await
+        .map_err(|_| anyhow!("timed out waiting for the browser sign-in to complete"))??;
+
+    subscriptions::exchange_authorization_code(client, issuer, &code, &pkce.verifier, &redirect_uri)
+        .await
+}
+
+fn open_browser(url: &str) {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    let _ = Command::new(opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+async fn wait_for_callback(listener: &TcpListener, expected_state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener
+            .accept()
+            .await
+            .context("failed to accept callback connection")?;
+        let Some(request_line) = read_request_line(&mut stream).await else {
+            continue;
+        };
+
+        match parse_callback(&request_line, expected_state) {
+            Callback::Ignored => {
+                respond(&mut stream, "404 Not Found", "Not found.").await;
+            }
+            Callback::Code(code) => {
+                respond(
+                    &mut stream,
+                    "200 OK",
+                    "Signed in to ox. You can close this tab and return to the terminal.",
+                )
+                .await;
+                return Ok(code);
+            }
+            Callback::Failed(message) => {
+                respond(&mut stream, "400 Bad Request", &message).await;
+                bail!(message);
+            }
+        }
+    }
+}
+
+async fn read_request_line(stream: &mut TcpStream) -> Option<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0_u8; 1024];
+    while !buf.windows(2).any(|window| window == b"\r\n") {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 || buf.len() > MAX_CALLBACK_REQUEST_BYTES {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    }
+    let text = String::from_utf8_lossy(&buf);
+    text.lines().next().map(str::to_string)
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/plain; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Callback {
+    Ignored,
+    Code(String),
+    Failed(String),
+}
+
+/// Interpret the request line of a redirect to the callback listener.
+pub(super) fn parse_callback(request_line: &str, expected_state: &str) -> Callback {
+    let mut parts = request_line.split_whitespace();
+    let (Some("GET"), Some(target)) = (parts.next(), parts.next()) else {
+        return Callback::Ignored;
+    };
+    let Ok(url) = reqwest::Url::parse(&format!("http://localhost{target}")) else {
+        return Callback::Ignored;
+    };
+    if url.path() != CALLBACK_PATH {
+        return Callback::Ignored;
+    }
+
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if let Some(error) = param("error") {
+        let description = param("error_description").unwrap_or_default();
+        return Callback::Failed(
+            format!("sign-in failed: {error} {description}")
+                .trim()
+                .to_string(),
+        );
+    }
+    if param("state").as_deref() != Some(expected_state) {
+        return Callback::Failed("sign-in failed: state mismatch".to_string());
+    }
+    param("code").map_or_else(
+        || Callback::Failed("sign-in failed: callback missing code".to_string()),
+        Callback::Code,
+    )
+}
+
+#[derive(Deserialize)]
+struct DeviceCodeResponse {
+    device_auth_id: String,
+    user_code: String,
+    #[serde(default)]
+    interval: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct DeviceTokenResponse {
+    authorization_code: String,
+    code_verifier: String,
+}
+
+/// Headless login: show a one-time code to enter on another device, then
+/// poll until the user approves it.
+pub(super) async fn device_login(
+    client: &reqwest::Client,
+    issuer: &str,
+) -> Result<RefreshedSubscriptionTokens> {
+    let response = client
+        .post(format!("{issuer}/api/accounts/deviceauth/usercode"))
+        .json(&serde_json::json!({ "client_id": CLIENT_ID }))
+        .send()
+        .await
+        .context("failed to request a device code")?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        bail!("device code request failed ({status}): {body}");
+    }
+    let device: DeviceCodeResponse = response
+        .json()
+        .await
+        .context("failed to parse device code response")?;
+    let interval = Duration::from_secs(
+        device
+            .interval
+            .as_ref()
+            .and_then(|value| value.as_u64().or_else(|| value.as_str()?.parse().ok()))
+            .unwrap_or(5),
+    );
+
+    eprintln!(
+        "To sign in, open {issuer}/codex/device and enter this code:\n\n  {}\n",
+        device.user_code
+    );
+
+    let started = Instant::now();
+    let approved = loop {
+        if started.elapsed() >= DEVICE_LOGIN_TIMEOUT {
+            bail!("timed out waiting for device code approval");
+        }
+        tokio::time::sleep(interval).await;
+
+        let response = client
+            .post(format!("{issuer}/api/accounts/deviceauth/token"))
+            .json(&serde_json::json!({
+                "device_auth_id": device.device_auth_id,
+                "user_code": device.user_code,
+            }))
+            .send()
+            .await
+            .context("failed to poll device code status")?;
+        match response.status() {
+            status if status.is_success() => {
+                break response
+                    .json::<DeviceTokenResponse>()
+                    .await
+                    .context("failed to parse device code approval")?;
+            }
+            reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND => {}
+            status => {
+                let body = response.text().await.unwrap_or_default();
+                bail!("device code login failed ({status}): {body}");
+            }
+        }
+    };
+
+    subscriptions::exchange_authorization_code(
+        client,
+        issuer,
+        &approved.authorization_code,
+        &approved.code_verifier,
+        &format!("{issuer}/deviceauth/callback"),
+    )
+    .await
+}
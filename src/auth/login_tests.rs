@@ -0,0 +1,84 @@
+use super::login::{Callback, device_login, parse_callback};
+use crate::test_support::spawn_server;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn parse_callback_returns_code_for_matching_state() {
+    let line = "GET /auth/callback?code=abc&state=s1 HTTP/1.1";
+    assert_eq!(
+        parse_callback(line, "s1"),
+        Callback::Code("abc".to_string())
+    );
+}
+
+#[test]
+fn parse_callback_rejects_state_mismatch() {
+    let line = "GET /auth/callback?code=abc&state=other HTTP/1.1";
+    assert_eq!(
+        parse_callback(line, "s1"),
+        Callback::Failed("sign-in failed: state mismatch".to_string())
+    );
+}
+
+#[test]
+fn parse_callback_reports_provider_error() {
+    let line = "GET /auth/callback?error=access_denied&error_description=nope HTTP/1.1";
+    assert_eq!(
+        parse_callback(line, "s1"),
+        Callback::Failed("sign-in failed: access_denied nope".to_string())
+    );
+}
+
+#[test]
+fn parse_callback_ignores_other_paths() {
+    assert_eq!(
+        parse_callback("GET /favicon.ico HTTP/1.1", "s1"),
+        Callback::Ignored
+    );
+}
+
+/// A stand-in auth server for the device-code flow. The first poll reports
+/// "pending" so the client has to retry.
+async fn spawn_auth_server(token_bodies: Arc<Mutex<Vec<String>>>) -> String {
+    let mut polls = 0;
+    spawn_server("application/json", move |request| {
+        let request_line = request.head.lines().next().unwrap_or_default();
+        if request_line.contains("/api/accounts/deviceauth/usercode") {
+            let reply = r#"{"device_auth_id":"dev-1","user_code":"ABCD-1234","interval":"0"}"#;
+            (200, reply.to_string())
+        } else if request_line.contains("/api/accounts/deviceauth/token") {
+            polls += 1;
+            if polls == 1 {
+                (403, "{}".to_string())
+            } else {
+                let reply = r#"{"authorization_code":"code-1","code_verifier":"verifier-1","code_challenge":"c"}"#;
+                (200, reply.to_string())
+            }
+        } else if request_line.contains("/oauth/token") {
+            token_bodies.lock().expect("lock").push(request.body);
+            let reply = r#"{"access_token":"access-1","refresh_token":"refresh-1","id_token":null}"#;
+            (200, reply.to_string())
+        } else {
+            (404, "{}".to_string())
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn device_login_exchanges_approved_code() {
+    let token_bodies = Arc::new(Mutex::new(Vec::new()));
+    let issuer = spawn_auth_server(Arc::clone(&token_bodies)).await;
+
+    let tokens = device_login(&reqwest::Client::new(), &issuer)
+        .await
+        .expect("device login should succeed");
+
+    assert_eq!(tokens.access_token, "access-1");
+    assert_eq!(tokens.refresh_token.as_deref(), Some("refresh-1"));
+    let bodies = token_bodies.lock().expect("lock").clone();
+    assert_eq!(bodies.len(), 1);
+    assert!(bodies[0].contains("grant_type=authorization_code"));
+    assert!(bodies[0].contains("code=code-1"));
+    assert!(bodies[0].contains("code_verifier=verifier-1"));
+}
@@ -1,13 +1,36 @@
+mod api_key;
+#[cfg(test)]
+mod api_key_tests;
+mod cache;
 mod config;
+#[cfg(test)]
+mod config_tests;
+mod login;
+#[cfg(test)]
+mod login_tests;
 mod store;
+#[cfg(test)]
+mod store_tests;
 mod subscriptions;
 
 use anyhow::{Context, Result};
+use cache::CachedAuth;
 use config::AuthMode;
 use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub use config::AuthConfig;
 
+/// Variables ox reads credentials from, or the command that prints one.
+/// Commands run by tools never see them.
+pub const CREDENTIAL_VARS: &[&str] = &[
+    "OPENAI_API_KEY",
+    "ANTHROPIC_API_KEY",
+    "AZURE_OPENAI_API_KEY",
+    "OX_API_KEY_COMMAND",
+];
+
+#[derive(Clone)]
 struct SubscriptionAuth {
     access_token: String,
     account_id: String,
@@ -15,32 +38,237 @@ struct SubscriptionAuth {
 
 impl AuthConfig {
     pub async fn build_headers(&self, client: &reqwest::Client) -> Result<HeaderMap> {
+        let mut headers = match self.mode() {
+            AuthMode::ApiKey => {
+                let key = self.api_key("OPENAI_API_KEY").await?;
+                key_headers(AUTHORIZATION.as_str(), &format!("Bearer {key}"))?
+            }
+            AuthMode::Anthropic => {
+                key_headers("x-api-key", &self.api_key("ANTHROPIC_API_KEY").await?)?
+            }
+            AuthMode::Azure => {
+                key_headers("api-key", &self.api_key("AZURE_OPENAI_API_KEY").await?)?
+            }
+            AuthMode::Subscription => self.build_subscription_headers(client).await?,
+            AuthMode::None => HeaderMap::new(),
+        };
+        headers.extend(self.extra_headers().clone());
+        Ok(headers)
+    }
+
+    /// The API key from `OX_API_KEY_COMMAND` (run once, then cached) or,
+    /// when no command is configured, from `env_var`.
+    async fn api_key(&self, env_var: &str) -> Result<String> {
+        let Some(command) = self.api_key_command() else {
+            return std::env::var(env_var).with_context(|| format!("{env_var} not set"));
+        };
+        self.api_key_cache()
+            .get_or_try_init(|| api_key::run_api_key_command(command.to_string()))
+            .await
+            .cloned()
+    }
+
+    /// Whether a 401 can be recovered by refreshing credentials.
+    pub const fn can_refresh(&self) -> bool {
+        matches!(self.mode(), AuthMode::Subscription)
+    }
+
+    /// Refresh subscription tokens even if their `exp` claim looks valid,
+    /// e.g. after the server rejected them, and persist the result.
+    pub async fn force_refresh(&self, client: &reqwest::Client) -> Result<()> {
         match self.mode() {
-            AuthMode::ApiKey => api_key_headers(),
-            AuthMode::Subscription => build_subscription_headers(client).await,
+            AuthMode::ApiKey | AuthMode::Anthropic | AuthMode::Azure | AuthMode::None => Ok(()),
+            AuthMode::Subscription => self.load_subscription_auth(client, true).await.map(|_| ()),
+        }
+    }
+
+    async fn load_subscription_auth(
+        &self,
+        client: &reqwest::Client,
+        force_refresh: bool,
+    ) -> Result<SubscriptionAuth> {
+        let mut cache = self.subscription_cache().lock().await;
+        if !force_refresh && let Some(cached) = cache.as_ref().filter(|cached| !cached.is_expired())
+        {
+            return Ok(cached.auth.clone());
+        }
+
+        let rejected = force_refresh
+            .then(|| {
+                cache
+                    .as_ref()
+                    .map(|cached| cached.auth.access_token.clone())
+            })
+            .flatten();
+        let auth = read_subscription_auth(self, client, force_refresh, rejected.as_deref()).await?;
+        *cache = Some(CachedAuth::new(auth.clone()));
+        drop(cache);
+        Ok(auth)
+    }
+
+    async fn build_subscription_headers(&self, client: &reqwest::Client) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        let auth = self.load_subscription_auth(client, false).await?;
+        insert_header(
+            &mut headers,
+            AUTHORIZATION.as_str(),
+            &format!("Bearer {}", auth.access_token),
+        )?;
+        insert_header(&mut headers, "chatgpt-account-id", &auth.account_id)?;
+        headers.insert(
+            HeaderName::from_static("openai-beta"),
+            HeaderValue::from_static("responses=experimental"),
+        );
+        headers.insert(
+            HeaderName::from_static("accept"),
+            HeaderValue::from_static("text/event-stream"),
+        );
+        headers.insert(
+            HeaderName::from_static("originator"),
+            HeaderValue::from_static("ox"),
+        );
+        Ok(headers)
+    }
+
+    /// Sign in to a `ChatGPT` subscription and store the tokens where
+    /// subscription mode reads them. Uses the browser flow unless
+    /// `device_code` is set or the callback port is unavailable.
+    pub async fn login(&self, client: &reqwest::Client, device_code: bool) -> Result<()> {
+        let issuer = self.issuer();
+        let tokens = if device_code {
+            login::device_login(client, issuer).await?
+        } else {
+            match login::bind_callback_listener().await {
+                Ok(listener) => login::browser_login(client, issuer, listener).await?,
+                Err(e) => {
+                    eprintln!("Warning: {e:#}; falling back to device code login.");
+                    login::device_login(client, issuer).await?
+                }
+            }
+        };
+
+        let _lock = lock_auth_file(self.store()).await?;
+        let mut auth_file = self.store().load_auth_file_if_exists()?.unwrap_or_default();
+        let account_id = tokens.account_id.clone();
+        auth_file.tokens = Some(store::CodexTokens {
+            access_token: Some(tokens.access_token),
+            refresh_token: tokens.refresh_token,
+            account_id: tokens.account_id,
+            id_token: tokens.id_token,
+            extra: std::collections::BTreeMap::new(),
+        });
+        self.store().save_auth_file(&auth_file)?;
+
+        println!(
+            "Signed in (account {}). Tokens saved to {}",
+            account_id.as_deref().unwrap_or("unknown"),
+            self.store().auth_path()?.display()
+        );
+        Ok(())
+    }
+
+    /// Forget the subscription tokens. `auth.json` is shared with the Codex
+    /// CLI, so anything else in it is kept; the file is only removed when
+    /// nothing else is left.
+    pub async fn logout(&self) -> Result<()> {
+        let _lock = lock_auth_file(self.store()).await?;
+        let path = self.store().auth_path()?;
+        let Some(mut auth_file) = self
+            .store()
+            .load_auth_file_if_exists()?
+            .filter(|file| file.tokens.is_some())
+        else {
+            println!("Not signed in (no tokens in {}).", path.display());
+            return Ok(());
+        };
+        auth_file.tokens = None;
+        auth_file.extra.remove("last_refresh");
+        if auth_file.extra.is_empty() {
+            self.store().remove_auth_file()?;
+            println!("Signed out. Removed {}", path.display());
+        } else {
+            self.store().save_auth_file(&auth_file)?;
+            println!("Signed out. Removed the tokens from {}", path.display());
         }
+        Ok(())
+    }
+
+    pub fn print_status(&self) -> Result<()> {
+        let path = self.store().auth_path()?;
+        let Some(tokens) = self
+            .store()
+            .load_auth_file_if_exists()?
+            .and_then(|file| file.tokens)
+        else {
+            println!("Not signed in (no tokens in {}).", path.display());
+            return Ok(());
+        };
+
+        let access_token = tokens.access_token.as_deref();
+        let account_id = tokens
+            .account_id
+            .clone()
+            .or_else(|| access_token.and_then(subscriptions::extract_account_id_from_jwt));
+        let expiry = access_token.and_then(subscriptions::access_token_expiry);
+
+        println!("Auth file: {}", path.display());
+        println!("Account: {}", account_id.as_deref().unwrap_or("unknown"));
+        println!(
+            "Access token: {}",
+            expiry.map_or_else(|| "missing or unreadable".to_string(), describe_expiry)
+        );
+        println!(
+            "Refresh token: {}",
+            if tokens.refresh_token.is_some() {
+                "present"
+            } else {
+                "missing"
+            }
+        );
+        Ok(())
     }
 }
 
-async fn get_valid_tokens(
-    client: &reqwest::Client,
-    tokens: &mut store::CodexTokens,
-) -> Result<bool> {
-    let access_token = tokens
-        .access_token
-        .as_deref()
-        .context("missing access_token in Codex auth tokens; run `codex login`")?;
+fn describe_expiry(exp: i64) -> String {
+    let expires_at = UNIX_EPOCH + Duration::from_secs(u64::try_from(exp).unwrap_or(0));
+    let date = httpdate::fmt_http_date(expires_at);
+    expires_at.duration_since(SystemTime::now()).map_or_else(
+        |_| format!("expired {date} (refreshed on next use)"),
+        |left| format!("expires {date} (in {}m)", left.as_secs() / 60),
+    )
+}
 
-    if !subscriptions::is_access_token_expired(access_token) {
-        return Ok(false);
+async fn lock_auth_file(store: &store::AuthStore) -> Result<store::AuthFileLock> {
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || store.lock_auth_file())
+        .await
+        .context("auth file lock task panicked")?
+}
+
+/// A refresh is needed when the token is expired, or when a forced refresh
+/// was requested and the file still holds the token the server rejected.
+/// With no rejected token known, a forced refresh always goes ahead.
+fn needs_refresh(tokens: &store::CodexTokens, force_refresh: bool, rejected: Option<&str>) -> bool {
+    let Some(access_token) = tokens.access_token.as_deref() else {
+        return true;
+    };
+    if subscriptions::is_access_token_expired(access_token) {
+        return true;
     }
+    force_refresh && rejected.is_none_or(|rejected| rejected == access_token)
+}
 
+async fn refresh_tokens(
+    client: &reqwest::Client,
+    issuer: &str,
+    tokens: &mut store::CodexTokens,
+) -> Result<()> {
     let refresh_token = tokens
         .refresh_token
         .as_deref()
-        .context("missing refresh_token in Codex auth tokens; run `codex login`")?;
+        .context("missing refresh_token in Codex auth tokens; run `ox login`")?;
 
-    let refreshed = subscriptions::refresh_access_token(client, refresh_token).await?;
+    let refreshed = subscriptions::refresh_access_token(client, issuer, refresh_token).await?;
     tokens.access_token = Some(refreshed.access_token.clone());
 
     if let Some(next_refresh_token) = refreshed.refresh_token {
@@ -55,31 +283,53 @@ async fn get_valid_tokens(
         .or_else(|| subscriptions::extract_account_id_from_jwt(&refreshed.access_token))
         .or_else(|| tokens.account_id.clone());
 
-    Ok(true)
+    Ok(())
 }
 
-async fn load_subscription_auth(client: &reqwest::Client) -> Result<SubscriptionAuth> {
-    let mut auth_file = store::load_auth_file()?;
-    let mut tokens = auth_file
+fn take_tokens(auth_file: &mut store::CodexAuthFile) -> Result<store::CodexTokens> {
+    auth_file
         .tokens
         .take()
-        .context("missing tokens in Codex auth file; run `codex login`")?;
+        .context("missing tokens in Codex auth file; run `ox login`")
+}
+
+async fn read_subscription_auth(
+    auth: &AuthConfig,
+    client: &reqwest::Client,
+    force_refresh: bool,
+    rejected: Option<&str>,
+) -> Result<SubscriptionAuth> {
+    let store = auth.store();
+    let tokens = take_tokens(&mut store.load_auth_file()?)?;
+    if !needs_refresh(&tokens, force_refresh, rejected) {
+        return subscription_auth(&tokens);
+    }
+
+    let _lock = lock_auth_file(store).await?;
 
-    let did_refresh = get_valid_tokens(client, &mut tokens).await?;
-    if did_refresh {
+    // Another process may have refreshed while we waited for the lock; reuse
+    // its tokens instead of spending the refresh token a second time.
+    let mut auth_file = store.load_auth_file()?;
+    let mut tokens = take_tokens(&mut auth_file)?;
+    if needs_refresh(&tokens, force_refresh, rejected) {
+        refresh_tokens(client, auth.issuer(), &mut tokens).await?;
         auth_file.tokens = Some(tokens.clone());
-        store::save_auth_file(&auth_file)?;
+        store.save_auth_file(&auth_file)?;
     }
 
+    subscription_auth(&tokens)
+}
+
+fn subscription_auth(tokens: &store::CodexTokens) -> Result<SubscriptionAuth> {
     let access_token = tokens
         .access_token
         .as_deref()
-        .context("missing access_token in Codex auth tokens; run `codex login`")?;
+        .context("missing access_token in Codex auth tokens; run `ox login`")?;
     let account_id = tokens
         .account_id
         .clone()
         .or_else(|| subscriptions::extract_account_id_from_jwt(access_token))
-        .context("missing account_id in Codex auth tokens; run `codex login`")?;
+        .context("missing account_id in Codex auth tokens; run `ox login`")?;
 
     Ok(SubscriptionAuth {
         access_token: access_token.to_string(),
@@ -95,37 +345,8 @@ fn insert_header(headers: &mut HeaderMap, name: &'static str, value: &str) -> Re
     Ok(())
 }
 
-fn api_key_headers() -> Result<HeaderMap> {
-    let mut headers = HeaderMap::new();
-    let key = std::env::var("OPENAI_API_KEY").context("OPENAI_API_KEY not set")?;
-    insert_header(
-        &mut headers,
-        AUTHORIZATION.as_str(),
-        &format!("Bearer {key}"),
-    )?;
-    Ok(headers)
-}
-
-async fn build_subscription_headers(client: &reqwest::Client) -> Result<HeaderMap> {
+fn key_headers(name: &'static str, value: &str) -> Result<HeaderMap> {
     let mut headers = HeaderMap::new();
-    let auth = load_subscription_auth(client).await?;
-    insert_header(
-        &mut headers,
-        AUTHORIZATION.as_str(),
-        &format!("Bearer {}", auth.access_token),
-    )?;
-    insert_header(&mut headers, "chatgpt-account-id", &auth.account_id)?;
-    headers.insert(
-        HeaderName::from_static("openai-beta"),
-        HeaderValue::from_static("responses=experimental"),
-    );
-    headers.insert(
-        HeaderName::from_static("accept"),
-        HeaderValue::from_static("text/event-stream"),
-    );
-    headers.insert(
-        HeaderName::from_static("originator"),
-        HeaderValue::from_static("ox"),
-    );
+    insert_header(&mut headers, name, value)?;
     Ok(headers)
 }
@@ -2,9 +2,15 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const AUTH_FILE_MODE: u32 = 0o600;
+const AUTH_DIR_MODE: u32 = 0o700;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(super) struct CodexAuthFile {
     pub(crate) tokens: Option<CodexTokens>,
     #[serde(flatten)]
@@ -21,29 +27,114 @@ pub(super) struct CodexTokens {
     pub(crate) extra: BTreeMap<String, Value>,
 }
 
-fn codex_home() -> Result<PathBuf> {
-    if let Ok(path) = std::env::var("CODEX_HOME") {
-        return Ok(PathBuf::from(path));
-    }
-    let home = std::env::var("HOME").context("HOME not set")?;
-    Ok(PathBuf::from(home).join(".codex"))
+/// Holds an advisory lock on `auth.json.lock` until dropped.
+pub(super) struct AuthFileLock {
+    _file: File,
 }
 
-fn auth_path() -> Result<PathBuf> {
-    Ok(codex_home()?.join("auth.json"))
+/// The Codex home directory holding `auth.json`. Defaults to `~/.codex`;
+/// `auth.codex_home` in the config (or `CODEX_HOME`) overrides it.
+#[derive(Clone)]
+pub(super) struct AuthStore {
+    home: Option<PathBuf>,
 }
 
-pub(super) fn load_auth_file() -> Result<CodexAuthFile> {
-    let path = auth_path()?;
-    let raw = std::fs::read_to_string(&path)
-        .with_context(|| format!("failed to read {}", path.display()))?;
-    serde_json::from_str(&raw).with_context(|| format!("invalid JSON in {}", path.display()))
-}
+impl AuthStore {
+    pub(super) const fn new(home: Option<PathBuf>) -> Self {
+        Self { home }
+    }
+
+    fn codex_home(&self) -> Result<PathBuf> {
+        if let Some(home) = &self.home {
+            return Ok(home.clone());
+        }
+        let home = std::env::var("HOME").context("HOME not set")?;
+        Ok(PathBuf::from(home).join(".codex"))
+    }
+
+    pub(super) fn auth_path(&self) -> Result<PathBuf> {
+        Ok(self.codex_home()?.join("auth.json"))
+    }
+
+    fn ensure_codex_home(&self) -> Result<()> {
+        let dir = self.codex_home()?;
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(AUTH_DIR_MODE)
+            .create(&dir)
+            .with_context(|| format!("failed to create {}", dir.display()))
+    }
 
-pub(super) fn save_auth_file(auth_file: &CodexAuthFile) -> Result<()> {
-    let path = auth_path()?;
-    let data = serde_json::to_string_pretty(auth_file)
-        .with_context(|| format!("failed to serialize {}", path.display()))?;
-    std::fs::write(&path, format!("{data}\n"))
-        .with_context(|| format!("failed to write {}", path.display()))
+    pub(super) fn load_auth_file(&self) -> Result<CodexAuthFile> {
+        let path = self.auth_path()?;
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&raw).with_context(|| format!("invalid JSON in {}", path.display()))
+    }
+
+    pub(super) fn load_auth_file_if_exists(&self) -> Result<Option<CodexAuthFile>> {
+        if !self.auth_path()?.exists() {
+            return Ok(None);
+        }
+        self.load_auth_file().map(Some)
+    }
+
+    /// Delete the auth file. Returns whether there was one to delete.
+    pub(super) fn remove_auth_file(&self) -> Result<bool> {
+        let path = self.auth_path()?;
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("failed to remove {}", path.display())),
+        }
+    }
+
+    /// Block until this process holds the auth file lock. The lock lives in a
+    /// sibling file because `save_auth_file` replaces `auth.json` by rename.
+    pub(super) fn lock_auth_file(&self) -> Result<AuthFileLock> {
+        self.ensure_codex_home()?;
+        let path = self.auth_path()?.with_extension("json.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .mode(AUTH_FILE_MODE)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        file.lock()
+            .with_context(|| format!("failed to lock {}", path.display()))?;
+        Ok(AuthFileLock { _file: file })
+    }
+
+    /// Write through a temp file and rename, so readers never see a partial
+    /// file. The result is always owner-only (0600).
+    pub(super) fn save_auth_file(&self, auth_file: &CodexAuthFile) -> Result<()> {
+        self.ensure_codex_home()?;
+        let path = self.auth_path()?;
+        let data = serde_json::to_string_pretty(auth_file)
+            .with_context(|| format!("failed to serialize {}", path.display()))?;
+        let tmp_path = path.with_extension(format!("json.tmp-{}", std::process::id()));
+
+        let write_tmp = || -> std::io::Result<()> {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .mode(AUTH_FILE_MODE)
+                .open(&tmp_path)?;
+            file.set_permissions(std::fs::Permissions::from_mode(AUTH_FILE_MODE))?;
+            file.write_all(format!("{data}\n").as_bytes())?;
+            file.sync_all()
+        };
+        if let Err(e) = write_tmp() {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e).with_context(|| format!("failed to write {}", tmp_path.display()));
+        }
+
+        if let Err(e) = std::fs::rename(&tmp_path, &path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e).with_context(|| format!("failed to replace {}", path.display()));
+        }
+        Ok(())
+    }
 }
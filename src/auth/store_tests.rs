@@ -0,0 +1,183 @@
+use super::cache::CachedAuth;
+use super::store::{AuthStore, CodexAuthFile, CodexTokens};
+use super::{AuthConfig, SubscriptionAuth, needs_refresh, read_subscription_auth};
+use crate::config::Config;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn codex_home() -> PathBuf {
+    std::env::temp_dir().join(format!("ox-codex-home-{:016x}", fastrand::u64(..)))
+}
+
+/// An unsigned JWT expiring `seconds` from now; only its claims are read.
+fn token(name: &str, seconds: i64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let exp = i64::try_from(now.as_secs()).unwrap() + seconds;
+    let encode = |json: serde_json::Value| URL_SAFE_NO_PAD.encode(json.to_string());
+    format!(
+        "{}.{}.sig",
+        encode(serde_json::json!({ "alg": "HS256", "typ": "JWT" })),
+        encode(serde_json::json!({ "exp": exp, "name": name }))
+    )
+}
+
+fn tokens(access_token: &str) -> CodexTokens {
+    CodexTokens {
+        access_token: Some(access_token.to_string()),
+        refresh_token: Some("refresh".to_string()),
+        account_id: Some("acct".to_string()),
+        id_token: None,
+        extra: BTreeMap::new(),
+    }
+}
+
+fn auth_file(access_token: &str) -> CodexAuthFile {
+    CodexAuthFile {
+        tokens: Some(tokens(access_token)),
+        extra: BTreeMap::from([("OPENAI_API_KEY".to_string(), serde_json::Value::Null)]),
+    }
+}
+
+fn mode(path: &Path) -> u32 {
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[test]
+fn saving_replaces_the_file_atomically_and_owner_only() {
+    let home = codex_home();
+    let store = AuthStore::new(Some(home.clone()));
+    let path = store.auth_path().unwrap();
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::write(&path, "{}").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    store.save_auth_file(&auth_file("first")).unwrap();
+    store.save_auth_file(&auth_file("second")).unwrap();
+
+    assert_eq!(mode(&path), 0o600);
+    let loaded = store.load_auth_file().unwrap();
+    assert_eq!(
+        loaded.tokens.and_then(|tokens| tokens.access_token),
+        Some("second".to_string())
+    );
+    assert!(loaded.extra.contains_key("OPENAI_API_KEY"));
+    let leftovers: Vec<_> = std::fs::read_dir(&home)
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.contains(".tmp-"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+
+    let fresh = AuthStore::new(Some(home.join("new")));
+    fresh.save_auth_file(&auth_file("first")).unwrap();
+    assert_eq!(mode(&home.join("new")), 0o700);
+    assert_eq!(mode(&fresh.auth_path().unwrap()), 0o600);
+    let _ = std::fs::remove_dir_all(home);
+}
+
+#[test]
+fn forced_refresh_skips_tokens_other_than_the_rejected_one() {
+    let valid = token("valid", 3600);
+    let current = tokens(&valid);
+
+    assert!(!needs_refresh(&current, false, None));
+    assert!(needs_refresh(&current, true, None));
+    assert!(needs_refresh(&current, true, Some(&valid)));
+    assert!(!needs_refresh(&current, true, Some("already-replaced")));
+    assert!(needs_refresh(&tokens(&token("old", -10)), false, None));
+    assert!(needs_refresh(&tokens("not-a-jwt"), false, None));
+}
+
+#[test]
+fn cached_auth_expires_with_its_access_token() {
+    let cached = |access_token: String| {
+        CachedAuth::new(SubscriptionAuth {
+            access_token,
+            account_id: "acct".to_string(),
+        })
+    };
+
+    assert!(!cached(token("valid", 3600)).is_expired());
+    assert!(cached(token("old", -10)).is_expired());
+    assert!(cached("not-a-jwt".to_string()).is_expired());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tokens_refreshed_while_waiting_for_the_lock_are_reused() {
+    let home = codex_home();
+    let rejected = token("rejected", 3600);
+    let replaced = token("replaced", 3600);
+    // Refreshing against this issuer would fail, so success means the
+    // tokens were re-read instead.
+    let config = Config::from_layers(
+        Vec::new(),
+        |_| None,
+        &[
+            ("auth.mode".to_string(), "subscription".to_string()),
+            ("auth.codex_home".to_string(), home.display().to_string()),
+            ("auth.issuer".to_string(), "http://127.0.0.1:9".to_string()),
+        ],
+    )
+    .unwrap();
+    let auth = AuthConfig::from_config(&config).unwrap();
+    let store = AuthStore::new(Some(home.clone()));
+    store.save_auth_file(&auth_file(&rejected)).unwrap();
+
+    let lock = store.lock_auth_file().unwrap();
+    let waiting = {
+        let (auth, rejected) = (auth.clone(), rejected.clone());
+        tokio::spawn(async move {
+            read_subscription_auth(&auth, &reqwest::Client::new(), true, Some(&rejected)).await
+        })
+    };
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    store.save_auth_file(&auth_file(&replaced)).unwrap();
+    drop(lock);
+
+    let refreshed = waiting.await.unwrap().expect("tokens should be re-read");
+    assert_eq!(refreshed.access_token, replaced);
+    let _ = std::fs::remove_dir_all(home);
+}
+
+#[tokio::test]
+async fn logout_keeps_what_else_is_in_the_auth_file() {
+    let home = codex_home();
+    let store = AuthStore::new(Some(home.clone()));
+    let config = Config::from_layers(
+        Vec::new(),
+        |_| None,
+        &[("auth.codex_home".to_string(), home.display().to_string())],
+    )
+    .unwrap();
+    let auth = AuthConfig::from_config(&config).unwrap();
+    let mut shared = auth_file("signed-in");
+    shared.extra = BTreeMap::from([
+        ("OPENAI_API_KEY".to_string(), serde_json::json!("sk-codex")),
+        (
+            "last_refresh".to_string(),
+            serde_json::json!("2026-01-01T00:00:00Z"),
+        ),
+    ]);
+    store.save_auth_file(&shared).unwrap();
+
+    auth.logout().await.unwrap();
+
+    let left = store.load_auth_file().unwrap();
+    assert!(left.tokens.is_none());
+    assert_eq!(
+        left.extra,
+        BTreeMap::from([("OPENAI_API_KEY".to_string(), serde_json::json!("sk-codex"))])
+    );
+
+    store.save_auth_file(&auth_file("signed-in")).unwrap();
+    let mut only_tokens = store.load_auth_file().unwrap();
+    only_tokens.extra.clear();
+    store.save_auth_file(&only_tokens).unwrap();
+    auth.logout().await.unwrap();
+    assert!(!store.auth_path().unwrap().exists());
+    let _ = std::fs::remove_dir_all(home);
+}
@@ -3,10 +3,14 @@ use jsonwebtoken::dangerous::insecure_decode;
 use serde::Deserialize;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-const TOKEN_URL: &str = "https://auth.openai.com/oauth/token";
-const CLIENT_ID: &str = "app_EMoamEEZ73f0CkXaXp7hrann";
+const TOKEN_PATH: &str = "/oauth/token";
+pub(super) const CLIENT_ID: &str = "app_EMoamEEZ73f0CkXaXp7hrann";
 const CLOCK_SKEW_SECONDS: i64 = 60;
 
+pub(super) fn token_url(issuer: &str) -> String {
+    format!("{issuer}{TOKEN_PATH}")
+}
+
 #[derive(Debug)]
 pub(super) struct RefreshedSubscriptionTokens {
     pub(super) access_token: String,
@@ -51,13 +55,11 @@ pub(super) fn extract_account_id_from_jwt(token: &str) -> Option<String> {
     decode_jwt_claims(token)?.auth?.chatgpt_account_id
 }
 
-pub(super) fn is_access_token_expired(token: &str) -> bool {
-    let Some(claims) = decode_jwt_claims(token) else {
-        return true;
-    };
-    let Some(exp) = claims.exp else {
-        return true;
-    };
+pub(super) fn access_token_expiry(token: &str) -> Option<i64> {
+    decode_jwt_claims(token)?.exp
+}
+
+pub(super) fn is_expiry_passed(exp: i64) -> bool {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_or(0_i64, |duration| {
@@ -66,38 +68,80 @@ pub(super) fn is_access_token_expired(token: &str) -> bool {
     now + CLOCK_SKEW_SECONDS >= exp
 }
 
+pub(super) fn is_access_token_expired(token: &str) -> bool {
+    access_token_expiry(token).is_none_or(is_expiry_passed)
+}
+
 pub(super) async fn refresh_access_token(
     client: &reqwest::Client,
+    issuer: &str,
     refresh_token: &str,
 ) -> Result<RefreshedSubscriptionTokens> {
-    let response = client
-        .post(TOKEN_URL)
-        .header("content-type", "application/x-www-form-urlencoded")
-        .form(&[
+    request_tokens(
+        client,
+        issuer,
+        &[
             ("grant_type", "refresh_token"),
             ("refresh_token", refresh_token),
             ("client_id", CLIENT_ID),
-        ])
+        ],
+        "token refresh",
+    )
+    .await
+}
+
+/// Exchange an OAuth authorization code (with its PKCE verifier) for tokens.
+pub(super) async fn exchange_authorization_code(
+    client: &reqwest::Client,
+    issuer: &str,
+    code: &str,
+    code_verifier: &str,
+    redirect_uri: &str,
+) -> Result<RefreshedSubscriptionTokens> {
+    request_tokens(
+        client,
+        issuer,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", CLIENT_ID),
+            ("code_verifier", code_verifier),
+        ],
+        "authorization code exchange",
+    )
+    .await
+}
+
+async fn request_tokens(
+    client: &reqwest::Client,
+    issuer: &str,
+    form: &[(&str, &str)],
+    action: &str,
+) -> Result<RefreshedSubscriptionTokens> {
+    let response = client
+        .post(token_url(issuer))
+        .header("content-type", "application/x-www-form-urlencoded")
+        .form(form)
         .send()
         .await
-        .context("failed to send token refresh request")?;
+        .with_context(|| format!("failed to send {action} request"))?;
 
     if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
-        return Err(anyhow!(
-            "subscription token refresh failed ({status}): {body}"
-        ));
+        return Err(anyhow!("subscription {action} failed ({status}): {body}"));
     }
 
     let payload: RefreshResponse = response
         .json()
         .await
-        .context("failed to parse token refresh response")?;
+        .with_context(|| format!("failed to parse {action} response"))?;
     let access_token = payload
         .access
-        .context("token refresh response missing access_token")?;
-    let account_id = extract_account_id_from_jwt(&access_token);
+        .with_context(|| format!("{action} response missing access_token"))?;
+    let account_id = extract_account_id_from_jwt(&access_token)
+        .or_else(|| payload.id.as_deref().and_then(extract_account_id_from_jwt));
 
     Ok(RefreshedSubscriptionTokens {
         access_token,
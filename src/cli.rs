@@ -1,20 +1,122 @@
 use anyhow::Result;
 
+pub enum Subcommand {
+    Login { device_code: bool },
+    Logout,
+    AuthStatus,
+    ConfigShow,
+    PermissionsCheck { tool: String, input: String },
+    McpServe,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    StreamJson,
+}
+
+/// Where a headless prompt comes from.
+pub enum Prompt {
+    Text(String),
+    Stdin,
+}
+
 pub struct CliArgs {
     pub session_name: String,
     pub list_sessions: bool,
+    pub subcommand: Option<Subcommand>,
+    /// `key=value` config overrides, applied on top of files and env.
+    pub overrides: Vec<(String, String)>,
+    /// Run one turn without the REPL (`-p`).
+    pub prompt: Option<Prompt>,
+    pub output_format: OutputFormat,
 }
 
 fn print_usage() {
-    println!("Usage: ox [--session <name>] [--list-sessions]");
+    println!("Usage: ox [--session <name>] [--list-sessions] [options]");
+    println!("       ox login [--device-code]");
+    println!("       ox logout");
+    println!("       ox auth status");
+    println!("       ox config show");
+    println!("       ox permissions check <tool> [<command>|<path>|<json>]");
+    println!("       ox mcp-serve");
+    println!();
+    println!("Options:");
+    println!("  -p, --prompt [<text>|-]    Run one turn and exit; reads stdin if no text");
+    println!("  --output-format <format>   text, json or stream-json (with -p)");
+    println!("  --model <name>             Model to use");
+    println!("  --provider <name>          responses, chat or anthropic");
+    println!("  --reasoning-effort <level> Reasoning effort, e.g. low or high");
+    println!("  -c, --config <key=value>   Override any config key");
+}
+
+fn parse_subcommand(name: &str, args: &mut impl Iterator<Item = String>) -> Result<Subcommand> {
+    let subcommand = match name {
+        "login" => {
+            let mut device_code = false;
+            for arg in args.by_ref() {
+                match arg.as_str() {
+                    "--device-code" => device_code = true,
+                    _ => anyhow::bail!("unknown argument for login: {arg}"),
+                }
+            }
+            Subcommand::Login { device_code }
+        }
+        "logout" => Subcommand::Logout,
+        "auth" => match args.next().as_deref() {
+            Some("status") => Subcommand::AuthStatus,
+            Some(other) => anyhow::bail!("unknown auth command: {other}"),
+            None => anyhow::bail!("missing auth command (expected: status)"),
+        },
+        "config" => match args.next().as_deref() {
+            Some("show") => Subcommand::ConfigShow,
+            Some(other) => anyhow::bail!("unknown config command: {other}"),
+            None => anyhow::bail!("missing config command (expected: show)"),
+        },
+        "permissions" => match args.next().as_deref() {
+            Some("check") => {
+                let Some(tool) = args.next() else {
+                    anyhow::bail!("missing tool for permissions check");
+                };
+                let input = args.by_ref().collect::<Vec<_>>().join(" ");
+                Subcommand::PermissionsCheck { tool, input }
+            }
+            Some(other) => anyhow::bail!("unknown permissions command: {other}"),
+            None => anyhow::bail!("missing permissions command (expected: check)"),
+        },
+        "mcp-serve" => Subcommand::McpServe,
+        _ => anyhow::bail!("unknown command: {name}"),
+    };
+
+    if let Some(extra) = args.next() {
+        anyhow::bail!("unexpected argument: {extra}");
+    }
+    Ok(subcommand)
 }
 
 pub fn parse_args() -> Result<CliArgs> {
-    let mut args = std::env::args().skip(1);
+    parse(std::env::args().skip(1))
+}
+
+/// Parse the arguments after the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliArgs> {
+    let mut args = args.into_iter().peekable();
     let mut session_name: Option<String> = None;
     let mut list_sessions = false;
+    let mut subcommand = None;
+    let mut overrides = Vec::new();
+    let mut prompt = None;
+    let mut output_format = None;
 
     while let Some(arg) = args.next() {
+        if let Some(key) = flag_config_key(&arg) {
+            let Some(value) = args.next() else {
+                anyhow::bail!("missing value for {arg}");
+            };
+            overrides.push((key.to_string(), value));
+            continue;
+        }
         match arg.as_str() {
             "--session" => {
                 let Some(name) = args.next() else {
@@ -25,16 +127,72 @@ pub fn parse_args() -> Result<CliArgs> {
             "--list-sessions" => {
                 list_sessions = true;
             }
+            "-c" | "--config" => {
+                let Some(pair) = args.next() else {
+                    anyhow::bail!("missing value for {arg}");
+                };
+                overrides.push(parse_override(&pair)?);
+            }
+            "-p" | "--prompt" => {
+                let text = args.next_if(|next| !next.starts_with('-'));
+                if text.is_none() {
+                    args.next_if_eq("-");
+                }
+                prompt = Some(text.map_or(Prompt::Stdin, Prompt::Text));
+            }
+            "--output-format" => {
+                let Some(format) = args.next() else {
+                    anyhow::bail!("missing value for --output-format");
+                };
+                output_format = Some(parse_output_format(&format)?);
+            }
             "--help" | "-h" => {
                 print_usage();
                 std::process::exit(0);
             }
+            name if !name.starts_with('-') && subcommand.is_none() => {
+                subcommand = Some(parse_subcommand(name, &mut args)?);
+            }
             _ => anyhow::bail!("unknown argument: {arg}"),
         }
     }
 
+    if output_format.is_some() && prompt.is_none() {
+        anyhow::bail!("--output-format requires -p");
+    }
+
     Ok(CliArgs {
-        session_name: session_name.unwrap_or_else(crate::session::create_session_name),
+        session_name: session_name.unwrap_or_else(ox::session::create_session_name),
         list_sessions,
+        subcommand,
+        overrides,
+        prompt,
+        output_format: output_format.unwrap_or(OutputFormat::Text),
     })
 }
+
+fn parse_output_format(format: &str) -> Result<OutputFormat> {
+    match format {
+        "text" => Ok(OutputFormat::Text),
+        "json" => Ok(OutputFormat::Json),
+        "stream-json" => Ok(OutputFormat::StreamJson),
+        _ => anyhow::bail!("unknown output format: {format} (expected text, json or stream-json)"),
+    }
+}
+
+/// Shorthand flags for common config keys.
+fn flag_config_key(flag: &str) -> Option<&'static str> {
+    match flag {
+        "--model" => Some("model"),
+        "--provider" => Some("provider"),
+        "--reasoning-effort" => Some("reasoning.effort"),
+        _ => None,
+    }
+}
+
+fn parse_override(pair: &str) -> Result<(String, String)> {
+    let Some((key, value)) = pair.split_once('=') else {
+        anyhow::bail!("expected key=value for --config, got '{pair}'");
+    };
+    Ok((key.trim().to_string(), value.trim().to_string()))
+}
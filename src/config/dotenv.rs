@@ -0,0 +1,26 @@
+use std::sync::OnceLock;
+
+static LOADED: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Load `.env` from the working directory or its parents, without
+/// overriding variables that are already set. The variables it sets are
+/// for ox alone: commands run by tools never see them.
+pub fn load_dotenv() {
+    LOADED.get_or_init(|| {
+        let Ok(entries) = dotenvy::dotenv_iter() else {
+            return Vec::new();
+        };
+        let names = entries
+            .flatten()
+            .map(|(name, _)| name)
+            .filter(|name| std::env::var_os(name).is_none())
+            .collect();
+        dotenvy::dotenv().ok();
+        names
+    });
+}
+
+/// The variables [`load_dotenv`] set.
+pub fn dotenv_names() -> &'static [String] {
+    LOADED.get().map_or(&[], Vec::as_slice)
+}
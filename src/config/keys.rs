@@ -0,0 +1,153 @@
+/// How a key's value is parsed when it comes from env or a CLI override.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Kind {
+    String,
+    Integer,
+    Boolean,
+    /// A table of strings; env and CLI values are JSON objects.
+    Table,
+    /// A table of tables whose contents are validated by the code that
+    /// reads them; env and CLI values are JSON objects.
+    Tables,
+    /// A list of strings. Every layer adds to the list instead of
+    /// replacing it; env and CLI values are JSON arrays.
+    List,
+}
+
+pub(super) struct KeySpec {
+    pub(super) key: &'static str,
+    pub(super) env: Option<&'static str>,
+    pub(super) kind: Kind,
+    pub(super) default: Option<DefaultValue>,
+    /// Only the user config, env and CLI may set it. A project file could
+    /// use such a key to run commands, send credentials to another host or
+    /// turn off a safety check.
+    pub(super) user_only: bool,
+}
+
+impl KeySpec {
+    const fn user_only(self) -> Self {
+        Self {
+            user_only: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum DefaultValue {
+    String(&'static str),
+    Integer(i64),
+    Boolean(bool),
+}
+
+const fn string(key: &'static str, env: &'static str) -> KeySpec {
+    KeySpec {
+        key,
+        env: Some(env),
+        kind: Kind::String,
+        default: None,
+        user_only: false,
+    }
+}
+
+const fn string_or(key: &'static str, env: &'static str, default: &'static str) -> KeySpec {
+    KeySpec {
+        key,
+        env: Some(env),
+        kind: Kind::String,
+        default: Some(DefaultValue::String(default)),
+        user_only: false,
+    }
+}
+
+const fn integer(key: &'static str, env: &'static str, default: i64) -> KeySpec {
+    KeySpec {
+        key,
+        env: Some(env),
+        kind: Kind::Integer,
+        default: Some(DefaultValue::Integer(default)),
+        user_only: false,
+    }
+}
+
+const fn boolean(key: &'static str, env: &'static str, default: bool) -> KeySpec {
+    KeySpec {
+        key,
+        env: Some(env),
+        kind: Kind::Boolean,
+        default: Some(DefaultValue::Boolean(default)),
+        user_only: false,
+    }
+}
+
+const fn list(key: &'static str, env: &'static str) -> KeySpec {
+    KeySpec {
+        key,
+        env: Some(env),
+        kind: Kind::List,
+        default: None,
+        user_only: false,
+    }
+}
+
+/// Every supported key, in the order `ox config show` prints them. Secrets
+/// such as API keys are deliberately env-only and not listed here.
+/// `user_only` keys are ignored in a project's `.ox/config.toml`.
+pub(super) const KEYS: &[KeySpec] = &[
+    string_or("auth.mode", "AUTH_MODE", "api"),
+    string("auth.codex_home", "CODEX_HOME").user_only(),
+    string_or("auth.issuer", "OX_AUTH_ISSUER", "https://auth.openai.com").user_only(),
+    string("model", "OPENAI_MODEL"),
+    string_or("provider", "OX_PROVIDER", "auto"),
+    string("base_url", "OX_BASE_URL").user_only(),
+    string("api_key_command", "OX_API_KEY_COMMAND").user_only(),
+    KeySpec {
+        key: "extra_headers",
+        env: Some("OX_EXTRA_HEADERS"),
+        kind: Kind::Table,
+        default: None,
+        user_only: true,
+    },
+    KeySpec {
+        key: "mcp_servers",
+        env: Some("OX_MCP_SERVERS"),
+        kind: Kind::Tables,
+        default: None,
+        user_only: true,
+    },
+    string("azure.endpoint", "AZURE_OPENAI_ENDPOINT").user_only(),
+    string("azure.deployment", "AZURE_OPENAI_DEPLOYMENT"),
+    string_or(
+        "azure.api_version",
+        "AZURE_OPENAI_API_VERSION",
+        "2025-04-01-preview",
+    ),
+    string("reasoning.effort", "OX_REASONING_EFFORT"),
+    string("reasoning.summary", "OX_REASONING_SUMMARY"),
+    integer("tools.max_tool_calls", "OX_MAX_TOOL_CALLS", 20),
+    integer("tools.max_parallel", "OX_MAX_PARALLEL_TOOLS", 8),
+    integer("tools.max_output_lines", "OX_MAX_OUTPUT_LINES", 2000),
+    integer("tools.max_output_bytes", "OX_MAX_OUTPUT_BYTES", 50 * 1024),
+    boolean("tools.strict", "OX_STRICT_TOOLS", false),
+    string_or("tools.approval", "OX_APPROVAL", "auto-read").user_only(),
+    list("permissions.allow", "OX_PERMISSIONS_ALLOW").user_only(),
+    list("permissions.deny", "OX_PERMISSIONS_DENY"),
+    list("workspace.roots", "OX_WORKSPACE_ROOTS").user_only(),
+    string_or("workspace.outside", "OX_WORKSPACE_OUTSIDE", "ask").user_only(),
+    list("tools.env.allow", "OX_ENV_ALLOW").user_only(),
+    list("tools.env.deny", "OX_ENV_DENY"),
+    boolean("redact.enabled", "OX_REDACT", true).user_only(),
+    list("redact.patterns", "OX_REDACT_PATTERNS"),
+    string_or("sandbox.mode", "OX_SANDBOX", "off").user_only(),
+    boolean("sandbox.network", "OX_SANDBOX_NETWORK", false).user_only(),
+    list("sandbox.writable_roots", "OX_SANDBOX_WRITABLE_ROOTS").user_only(),
+    integer("retry.max_attempts", "OX_MAX_ATTEMPTS", 5),
+    integer("retry.base_delay_ms", "OX_RETRY_BASE_MS", 1000),
+    integer("retry.max_delay_ms", "OX_RETRY_MAX_MS", 60_000),
+    string_or("sessions.dir", "OX_SESSION_DIR", ".sessions"),
+];
+
+pub(super) fn find(key: &str) -> Option<&'static KeySpec> {
+    KEYS.iter().find(|spec| spec.key == key)
+}
@@ -0,0 +1,331 @@
+mod dotenv;
+mod keys;
+#[cfg(test)]
+mod config_tests;
+
+use anyhow::{Context, Result, bail};
+use keys::{DefaultValue, KeySpec, Kind};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+pub use dotenv::{dotenv_names, load_dotenv};
+
+const PROJECT_CONFIG: &str = ".ox/config.toml";
+
+/// Where a config value came from, lowest precedence first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    User(PathBuf),
+    Project(PathBuf),
+    Env(&'static str),
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::User(path) => write!(f, "user config {}", path.display()),
+            Self::Project(path) => write!(f, "project config {}", path.display()),
+            Self::Env(name) => write!(f, "env {name}"),
+            Self::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+struct Entry {
+    value: Value,
+    source: Source,
+}
+
+/// Settings merged from defaults, `~/.config/ox/config.toml`, the nearest
+/// `.ox/config.toml`, environment variables and CLI overrides, in that
+/// order. Each value remembers which layer set it.
+///
+/// List keys collect the items of every layer, each with its own source.
+pub struct Config {
+    entries: BTreeMap<&'static str, Entry>,
+    lists: BTreeMap<&'static str, Vec<Entry>>,
+}
+
+impl Config {
+    pub fn load(overrides: &[(String, String)]) -> Result<Self> {
+        let mut files = Vec::new();
+        if let Some(path) = user_config_path()
+            && let Some(table) = read_table(&path)?
+        {
+            files.push((Source::User(path), table));
+        }
+        if let Some(path) = project_config_path()
+            && let Some(table) = read_table(&path)?
+        {
+            files.push((Source::Project(path), table));
+        }
+        Self::from_layers(files, |name| std::env::var(name).ok(), overrides)
+    }
+
+    /// Built-in defaults only, ignoring files and the environment.
+    pub fn defaults() -> Self {
+        let mut config = Self {
+            entries: BTreeMap::new(),
+            lists: BTreeMap::new(),
+        };
+        for spec in keys::KEYS {
+            if let Some(default) = spec.default {
+                let value = match default {
+                    DefaultValue::String(text) => Value::String(text.to_string()),
+                    DefaultValue::Integer(number) => Value::Integer(number),
+                    DefaultValue::Boolean(flag) => Value::Boolean(flag),
+                };
+                config.set(spec, value, Source::Default);
+            }
+        }
+        config
+    }
+
+    pub(crate) fn from_layers(
+        files: Vec<(Source, toml::Table)>,
+        env: impl Fn(&str) -> Option<String>,
+        overrides: &[(String, String)],
+    ) -> Result<Self> {
+        let mut config = Self::defaults();
+        for (source, table) in files {
+            config.merge_table(&table, "", &source)?;
+        }
+        for spec in keys::KEYS {
+            let Some(name) = spec.env else {
+                continue;
+            };
+            let Some(raw) = env(name).filter(|raw| !raw.trim().is_empty()) else {
+                continue;
+            };
+            let value = parse_raw(spec, raw.trim()).with_context(|| format!("invalid {name}"))?;
+            config.set(spec, value, Source::Env(name));
+        }
+        for (key, raw) in overrides {
+            let Some(spec) = keys::find(key) else {
+                bail!("unknown config key '{key}'");
+            };
+            let value = parse_raw(spec, raw).with_context(|| format!("invalid value for {key}"))?;
+            config.set(spec, value, Source::Cli);
+        }
+        Ok(config)
+    }
+
+    fn set(&mut self, spec: &'static KeySpec, value: Value, source: Source) {
+        if let (Kind::List, Value::Array(items)) = (spec.kind, &value) {
+            let list = self.lists.entry(spec.key).or_default();
+            for item in items {
+                list.push(Entry {
+                    value: item.clone(),
+                    source: source.clone(),
+                });
+            }
+            return;
+        }
+        self.entries.insert(spec.key, Entry { value, source });
+    }
+
+    fn merge_table(&mut self, table: &toml::Table, prefix: &str, source: &Source) -> Result<()> {
+        for (name, value) in table {
+            let key = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{prefix}.{name}")
+            };
+            if let Some(spec) = keys::find(&key) {
+                if spec.user_only && matches!(source, Source::Project(_)) {
+                    eprintln!(
+                        "Warning: ignoring '{key}' in {source}; only the user config, env or command line may set it"
+                    );
+                    continue;
+                }
+                check_kind(spec, value).with_context(|| format!("invalid '{key}' in {source}"))?;
+                self.set(spec, value.clone(), source.clone());
+            } else if let Value::Table(nested) = value {
+                self.merge_table(nested, &key, source)?;
+            } else {
+                eprintln!("Warning: ignoring unknown config key '{key}' in {source}");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn string(&self, key: &str) -> Option<&str> {
+        self.entries.get(key)?.value.as_str()
+    }
+
+    /// Integer keys always have a default, so this only returns 0 for a
+    /// key that is not registered.
+    pub fn usize(&self, key: &str) -> usize {
+        self.entries
+            .get(key)
+            .and_then(|entry| entry.value.as_integer())
+            .and_then(|number| usize::try_from(number).ok())
+            .unwrap_or(0)
+    }
+
+    /// Boolean keys always have a default, so this only returns false for
+    /// a key that is not registered.
+    pub fn bool(&self, key: &str) -> bool {
+        self.entries
+            .get(key)
+            .and_then(|entry| entry.value.as_bool())
+            .unwrap_or(false)
+    }
+
+    pub fn table(&self, key: &str) -> Vec<(String, String)> {
+        let Some(Value::Table(table)) = self.entries.get(key).map(|entry| &entry.value) else {
+            return Vec::new();
+        };
+        table
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+            .collect()
+    }
+
+    /// Every item of a list key, lowest-precedence layer first.
+    pub fn list(&self, key: &str) -> impl Iterator<Item = (&str, &Source)> {
+        self.lists
+            .get(key)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some((entry.value.as_str()?, &entry.source)))
+    }
+
+    /// Deserialize a structured key such as `mcp_servers`, naming the layer
+    /// that set it when the value does not fit `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let Some(entry) = self.entries.get(key) else {
+            return Ok(None);
+        };
+        entry
+            .value
+            .clone()
+            .try_into()
+            .map(Some)
+            .with_context(|| format!("invalid '{key}' in {}", entry.source))
+    }
+
+    /// Print every key with its merged value and the layer it came from.
+    pub fn show(&self) {
+        for spec in keys::KEYS {
+            if spec.kind == Kind::List {
+                let mut items = self.list(spec.key).peekable();
+                if items.peek().is_none() {
+                    println!("# {} is empty", spec.key);
+                }
+                for (item, source) in items {
+                    let line = format!("{} += {}", spec.key, Value::from(item));
+                    println!("{line:<50} # {source}");
+                }
+                continue;
+            }
+            match self.entries.get(spec.key) {
+                Some(entry) => {
+                    let line = format!("{} = {}", spec.key, display_value(&entry.value));
+                    println!("{line:<50} # {}", entry.source);
+                }
+                None => println!("# {} is unset", spec.key),
+            }
+        }
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Table(table) => {
+            let pairs: Vec<String> = table
+                .iter()
+                .map(|(name, value)| format!("{name:?} = {value}"))
+                .collect();
+            format!("{{ {} }}", pairs.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+fn check_kind(spec: &KeySpec, value: &Value) -> Result<()> {
+    match (spec.kind, value) {
+        (Kind::String, Value::String(_)) | (Kind::Boolean, Value::Boolean(_)) => Ok(()),
+        (Kind::Integer, Value::Integer(number)) if *number > 0 => Ok(()),
+        (Kind::Integer, _) => bail!("expected a positive integer"),
+        (Kind::Boolean, _) => bail!("expected true or false"),
+        (Kind::Table, Value::Table(table)) if table.values().all(Value::is_str) => Ok(()),
+        (Kind::Table, _) => bail!("expected a table of strings"),
+        (Kind::Tables, Value::Table(table)) if table.values().all(Value::is_table) => Ok(()),
+        (Kind::Tables, _) => bail!("expected a table of tables"),
+        (Kind::List, Value::Array(items)) if items.iter().all(Value::is_str) => Ok(()),
+        (Kind::List, _) => bail!("expected a list of strings"),
+        (Kind::String, _) => bail!("expected a string"),
+    }
+}
+
+/// Parse an env or CLI value. Tables are given as JSON objects and lists
+/// as JSON arrays.
+fn parse_raw(spec: &KeySpec, raw: &str) -> Result<Value> {
+    let value = match spec.kind {
+        Kind::String => Value::String(raw.to_string()),
+        Kind::Integer => Value::Integer(raw.parse().context("expected an integer")?),
+        Kind::Boolean => match raw {
+            "true" | "1" => Value::Boolean(true),
+            "false" | "0" => Value::Boolean(false),
+            _ => bail!("expected true or false"),
+        },
+        Kind::Table => {
+            let json: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(raw).context("expected a JSON object")?;
+            let mut table = toml::Table::new();
+            for (name, value) in json {
+                let Some(text) = value.as_str() else {
+                    bail!("value for '{name}' must be a string");
+                };
+                table.insert(name, Value::String(text.to_string()));
+            }
+            Value::Table(table)
+        }
+        Kind::Tables => {
+            let json: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(raw).context("expected a JSON object")?;
+            Value::try_from(json).context("expected a JSON object of objects")?
+        }
+        Kind::List => {
+            let json: Vec<String> =
+                serde_json::from_str(raw).context("expected a JSON array of strings")?;
+            Value::Array(json.into_iter().map(Value::String).collect())
+        }
+    };
+    check_kind(spec, &value)?;
+    Ok(value)
+}
+
+fn read_table(path: &Path) -> Result<Option<toml::Table>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    toml::from_str(&raw)
+        .map(Some)
+        .with_context(|| format!("invalid TOML in {}", path.display()))
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("ox").join("config.toml"))
+}
+
+/// The nearest `.ox/config.toml` in the working directory or its parents.
+fn project_config_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG))
+        .find(|path| path.exists())
+}
@@ -0,0 +1,181 @@
+use crate::cli::{OutputFormat, Prompt};
+use anyhow::{Context, Result, bail};
+use ox::provider::Usage;
+use ox::{Agent, AgentEvent, EventSink};
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+/// Collects what the final report needs and, for `stream-json`, prints
+/// each event as a JSON line as it arrives.
+struct HeadlessSink<'a> {
+    format: OutputFormat,
+    out: &'a mut (dyn Write + Send),
+    final_text: String,
+    usage: Usage,
+    tool_calls: usize,
+    error: Option<String>,
+    hit_tool_limit: bool,
+    /// The retry attempt last reported, so each is reported once.
+    retry_attempt: Option<u32>,
+}
+
+impl<'a> HeadlessSink<'a> {
+    fn new(format: OutputFormat, out: &'a mut (dyn Write + Send)) -> Self {
+        Self {
+            format,
+            out,
+            final_text: String::new(),
+            usage: Usage::default(),
+            tool_calls: 0,
+            error: None,
+            hit_tool_limit: false,
+            retry_attempt: None,
+        }
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.error.clone().or_else(|| {
+            self.hit_tool_limit.then(|| {
+                "stopped at the tool-call limit without a final answer (tools.max_tool_calls)"
+                    .to_string()
+            })
+        })
+    }
+
+    fn stream(&mut self, line: &serde_json::Value) {
+        if self.format == OutputFormat::StreamJson {
+            self.emit_json(line);
+        }
+    }
+
+    /// Write one JSON object as a line.
+    fn emit_json(&mut self, line: &serde_json::Value) {
+        let _ = writeln!(self.out, "{line}");
+        let _ = self.out.flush();
+    }
+
+    /// Print the outcome in the requested format.
+    fn report(&mut self, session_name: &str) {
+        let failure = self.failure();
+        match self.format {
+            OutputFormat::Text => match &failure {
+                Some(error) => eprintln!("Error: {error}"),
+                None => {
+                    let _ = writeln!(self.out, "{}", self.final_text);
+                }
+            },
+            OutputFormat::Json | OutputFormat::StreamJson => {
+                let status = match (&failure, self.hit_tool_limit) {
+                    (None, _) => "success",
+                    (Some(_), true) => "tool_limit",
+                    (Some(_), false) => "error",
+                };
+                let result = serde_json::json!({
+                    "type": "result",
+                    "status": status,
+                    "result": self.final_text,
+                    "error": failure,
+                    "usage": self.usage,
+                    "tool_calls": self.tool_calls,
+                    "session": session_name,
+                });
+                self.emit_json(&result);
+            }
+        }
+    }
+}
+
+impl EventSink for HeadlessSink<'_> {
+    fn on_event(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::TextDelta(text) => self.stream(&serde_json::json!({
+                "type": "text_delta",
+                "text": text
+            })),
+            AgentEvent::MessageFinished { text } => self.final_text.clone_from(text),
+            AgentEvent::ToolCallStarted {
+                call_id,
+                name,
+                arguments,
+            } => {
+                self.tool_calls += 1;
+                self.stream(&serde_json::json!({
+                    "type": "tool_call",
+                    "call_id": call_id,
+                    "name": name,
+                    "arguments": arguments
+                }));
+            }
+            AgentEvent::ToolCallFinished {
+                call_id,
+                name,
+                output,
+                duration,
+            } => self.stream(&serde_json::json!({
+                "type": "tool_result",
+                "call_id": call_id,
+                "name": name,
+                "output": output,
+                "duration_ms": duration.as_millis()
+            })),
+            AgentEvent::Usage(usage) => self.usage += *usage,
+            AgentEvent::Error(error) => self.error = Some(error.clone()),
+            AgentEvent::Warning(warning) => eprintln!("Warning: {warning}"),
+            AgentEvent::Retrying {
+                reason,
+                attempt,
+                max_attempts,
+                remaining,
+            } => {
+                if self.retry_attempt.replace(*attempt) != Some(*attempt) {
+                    eprintln!(
+                        "Warning: {reason}. Retrying in {}s (attempt {attempt}/{max_attempts})",
+                        remaining.as_secs_f64().ceil()
+                    );
+                }
+            }
+            AgentEvent::TurnFinished { hit_tool_limit } => self.hit_tool_limit = *hit_tool_limit,
+            AgentEvent::TurnStarted => {}
+        }
+    }
+}
+
+/// Run a single prompt to completion and report the result in `format`.
+/// Fails (exit code 1) when the turn errors or stops at the tool-call limit.
+pub async fn run(agent: &mut Agent, prompt: Prompt, format: OutputFormat) -> Result<ExitCode> {
+    run_with(agent, prompt, format, &mut io::stdin(), &mut io::stdout()).await
+}
+
+/// [`run`], reading a `-p -` prompt from `input` and writing to `out`.
+pub async fn run_with(
+    agent: &mut Agent,
+    prompt: Prompt,
+    format: OutputFormat,
+    input: &mut (dyn Read + Send),
+    out: &mut (dyn Write + Send),
+) -> Result<ExitCode> {
+    let input = match prompt {
+        Prompt::Text(text) => text,
+        Prompt::Stdin => {
+            let mut text = String::new();
+            input
+                .read_to_string(&mut text)
+                .context("failed to read prompt from stdin")?;
+            text
+        }
+    };
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("empty prompt");
+    }
+
+    let mut sink = HeadlessSink::new(format, out);
+    crate::run_turn(agent, input, &mut sink).await;
+    sink.report(agent.session_name());
+
+    Ok(if sink.failure().is_some() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,194 @@
+use crate::cli::{self, OutputFormat, Prompt};
+use crate::headless::run_with;
+use crate::test_support::spawn_server;
+use ox::{Agent, Config};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+/// A streamed Chat Completions reply that says `text`.
+fn answer(text: &str) -> (u16, String) {
+    sse(&[
+        serde_json::json!({ "choices": [{ "delta": { "content": text } }] }),
+        serde_json::json!({ "choices": [{ "delta": {}, "finish_reason": "stop" }] }),
+    ])
+}
+
+/// A streamed Chat Completions reply that calls `ls`.
+fn ls_call() -> (u16, String) {
+    sse(&[
+        serde_json::json!({ "choices": [{ "delta": { "tool_calls": [{
+            "index": 0, "id": "call_1", "function": { "name": "ls", "arguments": "{}" }
+        }] } }] }),
+        serde_json::json!({ "choices": [{ "delta": {}, "finish_reason": "tool_calls" }] }),
+    ])
+}
+
+fn sse(chunks: &[serde_json::Value]) -> (u16, String) {
+    let mut body: String = chunks
+        .iter()
+        .map(|chunk| format!("data: {chunk}\n\n"))
+        .collect();
+    body.push_str("data: [DONE]\n\n");
+    (200, body)
+}
+
+/// A model API that sends `replies` in turn and keeps the request bodies.
+async fn spawn_model(replies: Vec<(u16, String)>, requests: Arc<Mutex<Vec<String>>>) -> String {
+    let mut replies = replies.into_iter();
+    spawn_server("text/event-stream", move |request| {
+        assert!(
+            request.head.starts_with("POST /chat/completions "),
+            "{}",
+            request.head
+        );
+        requests.lock().unwrap().push(request.body);
+        replies.next().unwrap_or((500, "{}".to_string()))
+    })
+    .await
+}
+
+/// Run one headless turn against a stub model that sends `replies`.
+async fn headless(
+    replies: Vec<(u16, String)>,
+    prompt: Prompt,
+    format: OutputFormat,
+    stdin: &str,
+) -> (ExitCode, String, Vec<String>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let url = spawn_model(replies, Arc::clone(&requests)).await;
+    let overrides: Vec<(String, String)> = [
+        ("auth.mode", "none"),
+        ("provider", "chat"),
+        ("base_url", url.as_str()),
+        ("model", "stub"),
+        ("retry.max_attempts", "1"),
+        ("tools.max_tool_calls", "2"),
+    ]
+    .iter()
+    .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+    .collect();
+    let config = Config::load(&overrides).expect("valid config");
+    let mut agent = Agent::builder().config(config).build().expect("agent");
+    let mut out = Vec::new();
+
+    let code = run_with(&mut agent, prompt, format, &mut stdin.as_bytes(), &mut out)
+        .await
+        .expect("turn should run");
+
+    let requests = requests.lock().unwrap().clone();
+    (
+        code,
+        String::from_utf8(out).expect("utf-8 output"),
+        requests,
+    )
+}
+
+fn lines(output: &str) -> Vec<serde_json::Value> {
+    output
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("JSON line"))
+        .collect()
+}
+
+#[tokio::test]
+async fn json_reports_one_result_object() {
+    let (code, output, _) = headless(
+        vec![answer("hello")],
+        Prompt::Text("hi".to_string()),
+        OutputFormat::Json,
+        "",
+    )
+    .await;
+
+    assert_eq!(code, ExitCode::SUCCESS);
+    let lines = lines(&output);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["type"], "result");
+    assert_eq!(lines[0]["status"], "success");
+    assert_eq!(lines[0]["result"], "hello");
+    assert_eq!(lines[0]["error"], serde_json::Value::Null);
+    assert_eq!(lines[0]["tool_calls"], 0);
+    assert_eq!(lines[0]["session"], "in-memory");
+    assert!(lines[0]["usage"]["input_tokens"].is_u64());
+}
+
+#[tokio::test]
+async fn stream_json_streams_events_before_the_result() {
+    let (code, output, _) = headless(
+        vec![ls_call(), answer("done")],
+        Prompt::Text("list files".to_string()),
+        OutputFormat::StreamJson,
+        "",
+    )
+    .await;
+
+    assert_eq!(code, ExitCode::SUCCESS);
+    let lines = lines(&output);
+    let types: Vec<&str> = lines
+        .iter()
+        .map(|line| line["type"].as_str().unwrap_or_default())
+        .collect();
+    assert_eq!(types, ["tool_call", "tool_result", "text_delta", "result"]);
+    assert_eq!(lines[0]["name"], "ls");
+    assert_eq!(lines[1]["call_id"], "call_1");
+    assert!(lines[1]["duration_ms"].is_u64());
+    assert_eq!(lines[2]["text"], "done");
+    assert_eq!(lines[3]["result"], "done");
+    assert_eq!(lines[3]["tool_calls"], 1);
+}
+
+#[tokio::test]
+async fn dash_reads_the_prompt_from_stdin() {
+    let args = ["-p", "-", "--output-format", "text"].map(String::from);
+    let parsed = cli::parse(args).expect("valid arguments");
+    assert!(matches!(parsed.prompt, Some(Prompt::Stdin)));
+
+    let (code, output, requests) = headless(
+        vec![answer("from the model")],
+        Prompt::Stdin,
+        parsed.output_format,
+        "  summarize this\n",
+    )
+    .await;
+
+    assert_eq!(code, ExitCode::SUCCESS);
+    assert_eq!(output, "from the model\n");
+    let request: serde_json::Value = serde_json::from_str(&requests[0]).expect("JSON request");
+    let messages = request["messages"].as_array().expect("messages");
+    assert_eq!(
+        messages.last().expect("user message")["content"],
+        "summarize this"
+    );
+}
+
+#[tokio::test]
+async fn turn_errors_and_the_tool_limit_fail_the_run() {
+    let (code, output, _) = headless(
+        vec![(500, "{}".to_string())],
+        Prompt::Text("hi".to_string()),
+        OutputFormat::Json,
+        "",
+    )
+    .await;
+    assert_eq!(code, ExitCode::FAILURE);
+    let result = &lines(&output)[0];
+    assert_eq!(result["status"], "error");
+    assert!(
+        result["error"]
+            .as_str()
+            .is_some_and(|error| error.contains("500")),
+        "{result}"
+    );
+
+    let (code, output, _) = headless(
+        vec![ls_call(), ls_call()],
+        Prompt::Text("loop".to_string()),
+        OutputFormat::Json,
+        "",
+    )
+    .await;
+    assert_eq!(code, ExitCode::FAILURE);
+    let result = &lines(&output)[0];
+    assert_eq!(result["status"], "tool_limit");
+    assert_eq!(result["tool_calls"], 2);
+}
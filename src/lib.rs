@@ -0,0 +1,30 @@
+//! A coding agent: a model, a set of tools and a session history.
+//!
+//! Build an [`Agent`] with [`Agent::builder`], then drive it one turn at a
+//! time with [`Agent::run_turn`].
+
+#![allow(clippy::must_use_candidate, clippy::missing_errors_doc)]
+
+pub mod agent;
+mod api;
+mod app_context;
+pub mod auth;
+pub mod config;
+pub mod mcp;
+pub mod permissions;
+mod prompt;
+pub mod provider;
+pub mod redact;
+pub mod sandbox;
+pub mod session;
+#[cfg(test)]
+mod test_support;
+pub mod tools;
+
+pub use agent::{
+    Agent, AgentBuilder, AgentEvent, Approval, ApprovalMode, ApprovalRequest, Approver, EventSink,
+};
+pub use config::Config;
+pub use session::{JsonlSessionStore, SessionStore};
+pub use tokio_util::sync::CancellationToken;
+pub use tools::{Tool, ToolContext, ToolRegistry};
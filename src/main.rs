@@ -1,28 +1,59 @@
-mod agent;
-mod api;
-mod app_context;
-mod auth;
 mod cli;
-mod prompt;
-mod session;
-mod tools;
+mod headless;
+#[cfg(test)]
+mod headless_tests;
+mod terminal;
+#[cfg(test)]
+#[path = "test_support/http.rs"]
+mod test_support;
 
-use app_context::AppContext;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use ox::auth::AuthConfig;
+use ox::{
+    Agent, CancellationToken, Config, EventSink, JsonlSessionStore, SessionStore, ToolRegistry,
+};
 use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
 use tokio::signal;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    dotenvy::dotenv().ok();
+async fn main() -> Result<ExitCode> {
+    ox::config::load_dotenv();
     let cli = cli::parse_args()?;
+    let config = Config::load(&cli.overrides)?;
+    if let Some(subcommand) = cli.subcommand {
+        run_subcommand(subcommand, &config).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let sessions_dir = Path::new(config.string("sessions.dir").unwrap_or_default());
+    let store = Arc::new(JsonlSessionStore::new(sessions_dir)?);
     if cli.list_sessions {
-        return session::list_sessions();
+        list_sessions(store.as_ref())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    eprintln!("Using session: {}", cli.session_name);
+    let mut tools = ToolRegistry::builtin();
+    ox::mcp::register_servers(&mut tools, &config).await?;
+    let builder = Agent::builder()
+        .config(config)
+        .tools(tools)
+        .session(store, cli.session_name);
+    if let Some(prompt) = cli.prompt {
+        // No one to ask: calls that need approval are denied.
+        let mut agent = builder.build()?;
+        return headless::run(&mut agent, prompt, cli.output_format).await;
     }
-    let mut session_state = session::open_session(&cli.session_name)?;
-    let app = AppContext::new();
+    let mut agent = builder.approver(terminal::TerminalApprover).build()?;
     let stdin = io::stdin();
-    eprintln!("Auth mode: {} | model: {}", app.auth.mode_name(), app.auth.model());
+    eprintln!(
+        "Auth mode: {} | provider: {} | model: {}",
+        agent.auth().mode_name(),
+        agent.provider_name(),
+        agent.auth().model()
+    );
 
     loop {
         print!("> ");
@@ -36,37 +67,73 @@ async fn main() -> Result<()> {
             break;
         }
 
-        session_state.append(serde_json::json!({
-            "role": "user",
-            "content": input
-        }))?;
+        run_turn(&mut agent, input, &mut terminal::TerminalSink::default()).await;
+    }
+    Ok(ExitCode::SUCCESS)
+}
 
-        let persist_start = session_state.history_len();
+fn list_sessions(store: &dyn SessionStore) -> Result<()> {
+    let sessions = store.list().context("unable to list sessions")?;
+    if sessions.is_empty() {
+        println!("No sessions found.");
+    } else {
+        for name in &sessions {
+            println!("{name}");
+        }
+    }
+    Ok(())
+}
 
-        tokio::select! {
-            run_result = agent::run(&app, session_state.history_mut()) => {
-                if let Err(e) = run_result {
-                    eprintln!("Error: {e}");
-                }
-            }
-            signal_result = signal::ctrl_c() => {
+async fn run_subcommand(subcommand: cli::Subcommand, config: &Config) -> Result<()> {
+    let auth = || AuthConfig::from_config(config);
+    match subcommand {
+        cli::Subcommand::Login { device_code } => {
+            auth()?.login(&reqwest::Client::new(), device_code).await
+        }
+        cli::Subcommand::Logout => auth()?.logout().await,
+        cli::Subcommand::AuthStatus => auth()?.print_status(),
+        cli::Subcommand::ConfigShow => {
+            config.show();
+            Ok(())
+        }
+        cli::Subcommand::PermissionsCheck { tool, input } => {
+            ox::permissions::print_check(config, &tool, &input)
+        }
+        cli::Subcommand::McpServe => {
+            let context = ox::ToolContext {
+                limits: ox::tools::OutputLimits::from_config(config),
+                sandbox: ox::sandbox::Sandbox::from_config(config)?,
+                env: ox::tools::CommandEnv::from_config(config),
+                workspace: ox::tools::Workspace::from_config(config)?,
+                ..ox::ToolContext::default()
+            };
+            let permissions = ox::permissions::Permissions::from_config(config)?;
+            let redactor = ox::redact::Redactor::from_config(config)?;
+            ox::mcp::serve(ToolRegistry::builtin(), context, permissions, redactor).await
+        }
+    }
+}
+
+/// Run one turn, feeding its events to `sink`. Ctrl+C cancels the turn;
+/// the agent then reports the interruption as an error event.
+async fn run_turn(agent: &mut Agent, input: &str, sink: &mut dyn EventSink) {
+    let cancel = CancellationToken::new();
+    let mut events = agent.run_turn(input, &cancel);
+    loop {
+        let event = tokio::select! {
+            event = events.next() => event,
+            signal_result = signal::ctrl_c(), if !cancel.is_cancelled() => {
                 match signal_result {
-                    Ok(()) => {
-                        eprintln!("\nInterrupted. Returning to prompt.");
-                    }
-                    Err(e) => {
-                        eprintln!("\nError waiting for Ctrl+C signal: {e}");
-                    }
+                    Ok(()) => eprintln!(),
+                    Err(e) => eprintln!("\nError waiting for Ctrl+C signal: {e}"),
                 }
+                cancel.cancel();
+                continue;
             }
-        }
-
-        if let Err(e) = session_state.persist_from(persist_start) {
-            eprintln!(
-                "Warning: failed to persist session entries for {}: {e}",
-                session_state.session_name()
-            );
-        }
+        };
+        let Some(event) = event else {
+            break;
+        };
+        sink.on_event(&event);
     }
-    Ok(())
 }
@@ -0,0 +1,158 @@
+use super::ServerConfig;
+use super::transport::Connection;
+use crate::tools::CommandEnv;
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// A tool as listed by `tools/list`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ListedTool {
+    pub(super) name: String,
+    #[serde(default)]
+    pub(super) description: Option<String>,
+    pub(super) input_schema: Value,
+    #[serde(default)]
+    pub(super) annotations: Annotations,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Annotations {
+    #[serde(default)]
+    pub(super) read_only_hint: bool,
+}
+
+/// One configured server. The connection is opened on first use and
+/// reopened, with a fresh handshake, after the server exits, times out or
+/// a request is abandoned midway.
+pub(super) struct McpClient {
+    name: String,
+    config: ServerConfig,
+    env: CommandEnv,
+    connection: Mutex<Option<Connection>>,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    pub(super) fn new(name: String, config: ServerConfig, env: CommandEnv) -> Self {
+        Self {
+            name,
+            config,
+            env,
+            connection: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub(super) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) async fn list_tools(&self) -> Result<Vec<ListedTool>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut params = serde_json::json!({});
+            if let Some(cursor) = &cursor {
+                params["cursor"] = cursor.clone().into();
+            }
+            let mut result = self.request("tools/list", params).await?;
+            let page: Vec<ListedTool> = serde_json::from_value(result["tools"].take())
+                .context("invalid tools/list result")?;
+            tools.extend(page);
+            cursor = result["nextCursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    pub(super) async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value> {
+        self.request(
+            "tools/call",
+            serde_json::json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    /// Send one request, connecting first if needed, all within the
+    /// server's timeout. The connection is taken out while the request is
+    /// in flight, so one that fails or is dropped midway is never reused.
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let mut slot = self.connection.lock().await;
+        let timeout = self.config.timeout();
+        let exchange = async {
+            let alive = slot
+                .take()
+                .and_then(|mut connection| connection.is_alive().then_some(connection));
+            let mut connection = match alive {
+                Some(connection) => connection,
+                None => self.connect().await?,
+            };
+            let response = self.send(&mut connection, method, params).await?;
+            *slot = Some(connection);
+            Ok::<_, anyhow::Error>(response)
+        };
+        let response = tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| anyhow!("timed out after {}s", timeout.as_secs()))??;
+
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            bail!("{method} failed: {message}");
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn send(
+        &self,
+        connection: &mut Connection,
+        method: &str,
+        params: Value,
+    ) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+        connection.request(id, &message).await
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        let mut connection = Connection::open(&self.config, &self.env)?;
+        let params = serde_json::json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "ox", "version": env!("CARGO_PKG_VERSION") }
+        });
+        let response = self
+            .send(&mut connection, "initialize", params)
+            .await
+            .context("initialize failed")?;
+        if let Some(error) = response.get("error") {
+            bail!(
+                "initialize failed: {}",
+                error["message"].as_str().unwrap_or("unknown error")
+            );
+        }
+        let version = response["result"]["protocolVersion"]
+            .as_str()
+            .unwrap_or(PROTOCOL_VERSION);
+        connection.set_protocol_version(version);
+        connection
+            .notify(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/initialized"
+            }))
+            .await?;
+        Ok(connection)
+    }
+}
@@ -0,0 +1,128 @@
+use super::{ServerConfig, register};
+use crate::config::Config;
+use crate::tools::{CommandEnv, ToolContext, ToolRegistry, execute};
+use std::collections::BTreeMap;
+
+/// A stdio MCP server in shell: it answers the handshake, lists tools over
+/// two pages and implements `echo`, `crash` (exits) and `hang` (stalls).
+const STUB_SERVER: &str = r#"
+echo "stub server starting"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{}},"serverInfo":{"name":"stub","version":"1"}}}\n' "$id" ;;
+    *'"tools/list"'*'"cursor"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"crash","inputSchema":{"type":"object"}},{"name":"hang","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+    *'"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echo text","inputSchema":{"type":"object","properties":{"text":{"type":"string"}}},"annotations":{"readOnlyHint":true}}],"nextCursor":"2"}}\n' "$id" ;;
+    *'"name":"echo"'*)
+      text=$(printf '%s' "$line" | sed -n 's/.*"text":"\([^"]*\)".*/\1/p')
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"echo: %s"}]}}\n' "$id" "$text" ;;
+    *'"name":"crash"'*) exit 1 ;;
+    *'"name":"hang"'*) sleep 5 ;;
+  esac
+done
+"#;
+
+fn stub_server(timeout_secs: u64) -> BTreeMap<String, ServerConfig> {
+    let config = ServerConfig {
+        command: Some("sh".to_string()),
+        args: vec!["-c".to_string(), STUB_SERVER.to_string()],
+        timeout_secs: Some(timeout_secs),
+        ..ServerConfig::default()
+    };
+    BTreeMap::from([("stub".to_string(), config)])
+}
+
+async fn call(registry: &ToolRegistry, name: &str, arguments: &str) -> Result<String, String> {
+    let tool = registry.get(name).cloned();
+    let name = name.to_string();
+    let arguments = arguments.to_string();
+    tokio::task::spawn_blocking(move || {
+        execute(tool.as_deref(), &name, &arguments, &ToolContext::default())
+    })
+    .await
+    .expect("tool should not panic")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn registers_and_calls_server_tools() {
+    let mut registry = ToolRegistry::default();
+    register(&mut registry, stub_server(10), &CommandEnv::default()).await;
+
+    let names: Vec<_> = registry
+        .definitions(false)
+        .iter()
+        .map(|definition| definition["name"].as_str().unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["mcp__stub__echo", "mcp__stub__crash", "mcp__stub__hang"]
+    );
+    assert!(!registry.is_mutating("mcp__stub__echo"));
+    assert!(registry.is_mutating("mcp__stub__crash"));
+
+    let output = call(&registry, "mcp__stub__echo", r#"{"text":"hi"}"#).await;
+    assert_eq!(output.as_deref(), Ok("echo: hi"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restarts_the_server_after_a_crash_or_timeout() {
+    let mut registry = ToolRegistry::default();
+    register(&mut registry, stub_server(1), &CommandEnv::default()).await;
+
+    let output = call(&registry, "mcp__stub__crash", "{}").await;
+    assert_eq!(
+        output.unwrap_err(),
+        "Error: MCP server 'stub': server exited"
+    );
+    let output = call(&registry, "mcp__stub__echo", r#"{"text":"again"}"#).await;
+    assert_eq!(output.as_deref(), Ok("echo: again"));
+
+    let output = call(&registry, "mcp__stub__hang", "{}").await;
+    assert_eq!(
+        output.unwrap_err(),
+        "Error: MCP server 'stub': timed out after 1s"
+    );
+    let output = call(&registry, "mcp__stub__echo", r#"{"text":"back"}"#).await;
+    assert_eq!(output.as_deref(), Ok("echo: back"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn skips_servers_that_fail_to_start() {
+    let config = ServerConfig {
+        command: Some("false".to_string()),
+        ..ServerConfig::default()
+    };
+    let mut registry = ToolRegistry::default();
+    register(
+        &mut registry,
+        BTreeMap::from([("broken".to_string(), config)]),
+        &CommandEnv::default(),
+    )
+    .await;
+
+    assert!(registry.definitions(false).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stdio_servers_get_the_filtered_environment() {
+    let config = Config::from_layers(
+        Vec::new(),
+        |_| None,
+        &[("tools.env.deny".to_string(), r#"["HOME"]"#.to_string())],
+    )
+    .expect("valid config");
+    let mut servers = stub_server(10);
+    let server = servers.get_mut("stub").expect("stub server");
+    // The stub exits before the handshake unless HOME was dropped and its
+    // own `env` was added.
+    server.args[1] = format!(r#"[ -z "$HOME" ] && [ "$EXTRA" = x ] || exit 1{STUB_SERVER}"#);
+    server.env = BTreeMap::from([("EXTRA".to_string(), "x".to_string())]);
+    let mut registry = ToolRegistry::default();
+    register(&mut registry, servers, &CommandEnv::from_config(&config)).await;
+
+    let output = call(&registry, "mcp__stub__echo", r#"{"text":"hi"}"#).await;
+    assert_eq!(output.as_deref(), Ok("echo: hi"));
+}
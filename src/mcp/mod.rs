@@ -0,0 +1,93 @@
+//! Model Context Protocol support.
+//!
+//! As a client, each server configured under `mcp_servers` is started once
+//! and its tools are offered to the model next to the built-in ones. As a
+//! server (`ox mcp-serve`), the built-in tools are offered to other clients.
+
+mod client;
+#[cfg(test)]
+mod client_tests;
+mod server;
+#[cfg(test)]
+mod server_tests;
+mod tool;
+mod transport;
+
+use crate::config::Config;
+use crate::tools::{CommandEnv, ToolRegistry};
+use anyhow::{Result, bail};
+use client::McpClient;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tool::McpTool;
+
+pub use server::serve;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// How to reach one server: `command` launches it over stdio, `url`
+/// speaks streamable HTTP.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables for a stdio server.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    pub url: Option<String>,
+    /// Extra headers for an HTTP server.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Limit for starting the server and for each request, including tool
+    /// calls. Defaults to 60 seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+impl ServerConfig {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+}
+
+/// Start every server in the `mcp_servers` config and register its tools.
+/// A server that fails to start is reported and skipped so the rest of ox
+/// still works.
+pub async fn register_servers(registry: &mut ToolRegistry, config: &Config) -> Result<()> {
+    let servers: BTreeMap<String, ServerConfig> = config.get("mcp_servers")?.unwrap_or_default();
+    for (name, server) in &servers {
+        if server.command.is_some() == server.url.is_some() {
+            bail!("MCP server '{name}' needs exactly one of 'command' or 'url'");
+        }
+    }
+    register(registry, servers, &CommandEnv::from_config(config)).await;
+    Ok(())
+}
+
+async fn register(
+    registry: &mut ToolRegistry,
+    servers: BTreeMap<String, ServerConfig>,
+    env: &CommandEnv,
+) {
+    let clients: Vec<Arc<McpClient>> = servers
+        .into_iter()
+        .map(|(name, server)| Arc::new(McpClient::new(name, server, env.clone())))
+        .collect();
+    let listed = futures::future::join_all(clients.iter().map(|client| client.list_tools())).await;
+    for (client, tools) in clients.iter().zip(listed) {
+        match tools {
+            Ok(tools) => {
+                for tool in tools {
+                    registry.insert(Arc::new(McpTool::new(Arc::clone(client), tool)));
+                }
+            }
+            Err(e) => eprintln!(
+                "Warning: MCP server '{}' is unavailable: {e:#}",
+                client.name()
+            ),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use crate::permissions::Permissions;
+use crate::redact::Redactor;
+use crate::tools::{self, ToolContext, ToolRegistry};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{RwLock, mpsc};
+use tokio_util::sync::CancellationToken;
+
+const SUPPORTED_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// The state shared by every request of one `ox mcp-serve` session.
+pub(super) struct Server {
+    tools: ToolRegistry,
+    /// The limits, sandbox and workspace every call runs with. Paths outside
+    /// the workspace are refused unless `workspace.outside` allows them,
+    /// since the client can't be asked about them.
+    context: ToolContext,
+    /// Deny rules apply as they do in the agent. Calls no rule denies are
+    /// left to the client, which does its own approval.
+    permissions: Permissions,
+    /// Output is redacted as it is for the agent, and placeholders in the
+    /// arguments of mutating calls are restored.
+    redactor: Redactor,
+    /// Cancellation tokens of running calls, keyed by JSON-RPC request id.
+    running: Mutex<HashMap<String, CancellationToken>>,
+    /// Read-only calls share this lock; mutating calls hold it alone, the
+    /// same ordering the agent applies to its own calls.
+    exclusive: RwLock<()>,
+}
+
+impl Server {
+    pub(super) fn new(
+        tools: ToolRegistry,
+        context: ToolContext,
+        permissions: Permissions,
+        redactor: Redactor,
+    ) -> Self {
+        Self {
+            tools,
+            context,
+            permissions,
+            redactor,
+            running: Mutex::new(HashMap::new()),
+            exclusive: RwLock::new(()),
+        }
+    }
+}
+
+/// Serve `tools` over stdio until the client closes stdin.
+pub async fn serve(
+    tools: ToolRegistry,
+    context: ToolContext,
+    permissions: Permissions,
+    redactor: Redactor,
+) -> Result<()> {
+    let server = Server::new(tools, context, permissions, redactor);
+    serve_on(
+        BufReader::new(tokio::io::stdin()),
+        tokio::io::stdout(),
+        server,
+    )
+    .await
+}
+
+pub(super) async fn serve_on(
+    reader: impl AsyncBufRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin + Send + 'static,
+    server: Server,
+) -> Result<()> {
+    let server = Arc::new(server);
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Value>();
+    let output = tokio::spawn(async move {
+        while let Some(message) = receiver.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            writer.write_all(line.as_bytes()).await?;
+            writer.flush().await?;
+        }
+        Ok::<_, std::io::Error>(())
+    });
+
+    let mut lines = reader.lines();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .context("failed to read from stdin")?
+    {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(message) => handle(&server, &message, &sender),
+            Err(e) => {
+                let _ = sender.send(error_response(&Value::Null, -32700, &format!("{e}")));
+            }
+        }
+    }
+
+    // Calls still running hold their own senders; output ends after them.
+    drop(sender);
+    output
+        .await
+        .context("output task failed")?
+        .context("failed to write to stdout")
+}
+
+fn handle(server: &Arc<Server>, message: &Value, out: &mpsc::UnboundedSender<Value>) {
+    let method = message["method"].as_str().unwrap_or_default();
+    let Some(id) = message.get("id").cloned() else {
+        if method == "notifications/cancelled" {
+            let request_id = message["params"]["requestId"].to_string();
+            if let Some(cancel) = lock(&server.running).get(&request_id) {
+                cancel.cancel();
+            }
+        }
+        return;
+    };
+
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let response = match method {
+        "initialize" => response(&id, initialize(&params)),
+        "ping" => response(&id, serde_json::json!({})),
+        "tools/list" => response(&id, list_tools(&server.tools)),
+        "tools/call" => {
+            let server = Arc::clone(server);
+            let out = out.clone();
+            tokio::spawn(async move {
+                let response = call_tool(&server, &id, &params).await;
+                let _ = out.send(response);
+            });
+            return;
+        }
+        "" => return, // a response to a request we never sent
+        _ => error_response(&id, -32601, &format!("method not found: {method}")),
+    };
+    let _ = out.send(response);
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params["protocolVersion"].as_str().unwrap_or_default();
+    let version = SUPPORTED_VERSIONS
+        .iter()
+        .find(|version| **version == requested)
+        .unwrap_or(&SUPPORTED_VERSIONS[0]);
+    serde_json::json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "ox", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+/// The same definitions the agent sends to the model, in MCP form.
+fn list_tools(tools: &ToolRegistry) -> Value {
+    let tools: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            let definition = tool.definition(false);
+            serde_json::json!({
+                "name": definition["name"],
+                "description": definition["description"],
+                "inputSchema": definition["parameters"],
+                "annotations": { "readOnlyHint": !tool.is_mutating() }
+            })
+        })
+        .collect();
+    serde_json::json!({ "tools": tools })
+}
+
+/// Run one call through `tools::execute`, exactly as the agent would.
+/// Tool failures are `isError` results; only an unknown tool is a protocol
+/// error.
+async fn call_tool(server: &Server, id: &Value, params: &Value) -> Value {
+    let name = params["name"].as_str().unwrap_or_default().to_string();
+    let Some(tool) = server.tools.get(&name).cloned() else {
+        return error_response(id, -32602, &format!("unknown tool: {name}"));
+    };
+    let arguments = params.get("arguments").cloned().unwrap_or_default();
+    if let Some(denial) = server.permissions.check(&name, &arguments).denial(&name) {
+        return tool_result(id, &denial, true);
+    }
+    let arguments = if arguments.is_null() {
+        "{}".to_string()
+    } else if tool.is_mutating() {
+        server.redactor.restore(&arguments.to_string())
+    } else {
+        arguments.to_string()
+    };
+    let ctx = ToolContext {
+        cancel: CancellationToken::new(),
+        ..server.context.clone()
+    };
+    lock(&server.running).insert(id.to_string(), ctx.cancel.clone());
+
+    let (_exclusive, _shared) = if tool.is_mutating() {
+        (Some(server.exclusive.write().await), None)
+    } else {
+        (None, Some(server.exclusive.read().await))
+    };
+    let output = tokio::task::spawn_blocking(move || {
+        tools::execute(Some(tool.as_ref()), &name, &arguments, &ctx)
+    })
+    .await;
+    lock(&server.running).remove(&id.to_string());
+
+    let output = output.unwrap_or_else(|_| Err("Error: tool panicked".to_string()));
+    let is_error = output.is_err();
+    let (output, _) = server
+        .redactor
+        .redact_output(&output.unwrap_or_else(|error| error));
+    tool_result(id, &output, is_error)
+}
+
+fn tool_result(id: &Value, output: &str, is_error: bool) -> Value {
+    response(
+        id,
+        serde_json::json!({
+            "content": [{ "type": "text", "text": output }],
+            "isError": is_error
+        }),
+    )
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn response(id: &Value, result: Value) -> Value {
+    let mut response = serde_json::json!({ "jsonrpc": "2.0", "id": id });
+    response["result"] = result;
+    response
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
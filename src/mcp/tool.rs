@@ -0,0 +1,140 @@
+use super::client::{ListedTool, McpClient};
+use crate::tools::{DynTool, OutputLimits, ToolContext};
+use serde_json::Value;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Function names may have at most 64 characters from `[A-Za-z0-9_-]`.
+const MAX_NAME_LEN: usize = 64;
+
+/// A server tool offered to the model as `mcp__<server>__<tool>`.
+pub(super) struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    remote_name: String,
+    description: String,
+    input_schema: Value,
+    read_only: bool,
+}
+
+impl McpTool {
+    pub(super) fn new(client: Arc<McpClient>, tool: ListedTool) -> Self {
+        Self {
+            name: qualified_name(client.name(), &tool.name),
+            client,
+            remote_name: tool.name,
+            description: tool.description.unwrap_or_default(),
+            input_schema: tool.input_schema,
+            read_only: tool.annotations.read_only_hint,
+        }
+    }
+}
+
+impl DynTool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Server schemas are passed through as-is and can't be assumed to be
+    /// strict-compatible, so these definitions are never strict.
+    fn definition(&self, _strict: bool) -> Value {
+        serde_json::json!({
+            "type": "function",
+            "name": self.name,
+            "description": self.description,
+            "parameters": self.input_schema,
+            "strict": false
+        })
+    }
+
+    /// Only tools the server marks read-only may run in parallel.
+    fn is_mutating(&self) -> bool {
+        !self.read_only
+    }
+
+    fn call(&self, arguments: &str, ctx: &ToolContext) -> Result<String, String> {
+        let arguments = if arguments.trim().is_empty() {
+            Value::Object(serde_json::Map::new())
+        } else {
+            match serde_json::from_str(arguments) {
+                Ok(arguments) => arguments,
+                Err(e) => {
+                    return Err(format!(
+                        "Error: invalid JSON in arguments for {}: {e}",
+                        self.name
+                    ));
+                }
+            }
+        };
+        // Tools run on a blocking thread, so waiting on the runtime is fine.
+        let result = tokio::runtime::Handle::current().block_on(async {
+            tokio::select! {
+                result = self.client.call_tool(&self.remote_name, arguments) => Some(result),
+                () = ctx.cancel.cancelled() => None,
+            }
+        });
+        match result {
+            None => Err("Error: tool call cancelled by user".to_string()),
+            Some(Ok(result)) => format_result(&result, ctx.limits),
+            Some(Err(e)) => Err(format!("Error: MCP server '{}': {e:#}", self.client.name())),
+        }
+    }
+}
+
+fn qualified_name(server: &str, tool: &str) -> String {
+    let mut name: String = format!("mcp__{server}__{tool}")
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.truncate(MAX_NAME_LEN);
+    name
+}
+
+/// Flatten a `tools/call` result into text for the model. Non-text content
+/// is summarized; `isError` results become `Err` with an `Error: ...`
+/// message.
+fn format_result(result: &Value, limits: OutputLimits) -> Result<String, String> {
+    let mut text = String::new();
+    for item in result["content"].as_array().into_iter().flatten() {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        match item["type"].as_str() {
+            Some("text") => text.push_str(item["text"].as_str().unwrap_or_default()),
+            Some("resource") => match item["resource"]["text"].as_str() {
+                Some(contents) => text.push_str(contents),
+                None => {
+                    let _ = write!(text, "[resource {}]", item["resource"]["uri"]);
+                }
+            },
+            Some("resource_link") => {
+                let _ = write!(text, "[resource {}]", item["uri"]);
+            }
+            Some(kind) => {
+                let _ = write!(text, "[{kind} {}]", item["mimeType"]);
+            }
+            None => {}
+        }
+    }
+    if text.is_empty()
+        && let Some(structured) = result.get("structuredContent")
+    {
+        text = structured.to_string();
+    }
+    let text = limits.head(&text, "lines remaining");
+    if result["isError"] == true {
+        Err(format!("Error: {text}"))
+    } else {
+        Ok(text)
+    }
+}
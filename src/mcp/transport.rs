@@ -0,0 +1,222 @@
+use super::ServerConfig;
+use crate::agent::stream::get_event;
+use crate::tools::CommandEnv;
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::process::Stdio as ChildStdio;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+/// A live connection to one server. Requests are sent one at a time, so
+/// a response is simply the next message carrying the request's id.
+pub(super) enum Connection {
+    Stdio(Stdio),
+    Http(Http),
+}
+
+impl Connection {
+    pub(super) fn open(config: &ServerConfig, env: &CommandEnv) -> Result<Self> {
+        match (&config.command, &config.url) {
+            (Some(command), _) => Stdio::spawn(command, config, env).map(Self::Stdio),
+            (None, Some(url)) => Ok(Self::Http(Http::new(url, config))),
+            (None, None) => bail!("no 'command' or 'url' configured"),
+        }
+    }
+
+    /// Send a request and wait for the response message with the same id.
+    pub(super) async fn request(&mut self, id: u64, message: &Value) -> Result<Value> {
+        match self {
+            Self::Stdio(stdio) => stdio.request(id, message).await,
+            Self::Http(http) => http.request(id, message).await,
+        }
+    }
+
+    pub(super) async fn notify(&mut self, message: &Value) -> Result<()> {
+        match self {
+            Self::Stdio(stdio) => stdio.write(message).await,
+            Self::Http(http) => http.post(message).await.map(drop),
+        }
+    }
+
+    /// Remember the negotiated protocol version; HTTP sends it as a header.
+    pub(super) fn set_protocol_version(&mut self, version: &str) {
+        if let Self::Http(http) = self {
+            http.protocol_version = Some(version.to_string());
+        }
+    }
+
+    /// Whether a stdio server is still running. HTTP is stateless enough
+    /// that failures surface on the next request instead.
+    pub(super) fn is_alive(&mut self) -> bool {
+        match self {
+            Self::Stdio(stdio) => matches!(stdio.child.try_wait(), Ok(None)),
+            Self::Http(_) => true,
+        }
+    }
+}
+
+/// A server process speaking newline-delimited JSON-RPC on stdin/stdout.
+/// The process is killed when the connection is dropped.
+pub(super) struct Stdio {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Stdio {
+    /// Start the server with the filtered environment plus its own `env`.
+    fn spawn(command: &str, config: &ServerConfig, env: &CommandEnv) -> Result<Self> {
+        let mut child = Command::new(command)
+            .args(&config.args)
+            .env_clear()
+            .envs(env.vars())
+            .envs(&config.env)
+            .stdin(ChildStdio::piped())
+            .stdout(ChildStdio::piped())
+            .stderr(ChildStdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("failed to start '{command}'"))?;
+        let stdin = child.stdin.take().context("server stdin unavailable")?;
+        let stdout = child.stdout.take().context("server stdout unavailable")?;
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    async fn write(&mut self, message: &Value) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .await
+            .context("failed to write to server")?;
+        self.stdin
+            .flush()
+            .await
+            .context("failed to write to server")
+    }
+
+    async fn request(&mut self, id: u64, message: &Value) -> Result<Value> {
+        self.write(message).await?;
+        loop {
+            let mut line = String::new();
+            let read = self
+                .stdout
+                .read_line(&mut line)
+                .await
+                .context("failed to read from server")?;
+            if read == 0 {
+                bail!("server exited");
+            }
+            // Servers may log to stdout; anything that isn't JSON is skipped.
+            let Ok(incoming) = serde_json::from_str::<Value>(line.trim()) else {
+                continue;
+            };
+            if incoming.get("method").is_none() && incoming["id"] == id {
+                return Ok(incoming);
+            }
+            if let Some(reply) = reply_to_server_request(&incoming) {
+                self.write(&reply).await?;
+            }
+        }
+    }
+}
+
+/// Answer requests the server sends us: `ping` succeeds, anything else
+/// is unsupported. Notifications get no reply.
+fn reply_to_server_request(incoming: &Value) -> Option<Value> {
+    let method = incoming["method"].as_str()?;
+    let id = incoming.get("id")?;
+    Some(if method == "ping" {
+        serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+    } else {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("method not supported: {method}") }
+        })
+    })
+}
+
+/// A server behind the streamable HTTP transport. Each message is a POST;
+/// the reply is either a JSON body or an event stream carrying it.
+pub(super) struct Http {
+    client: reqwest::Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    session_id: Option<String>,
+    protocol_version: Option<String>,
+}
+
+impl Http {
+    fn new(url: &str, config: &ServerConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            headers: config
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            session_id: None,
+            protocol_version: None,
+        }
+    }
+
+    async fn post(&mut self, message: &Value) -> Result<reqwest::Response> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("accept", "application/json, text/event-stream")
+            .json(message);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(session_id) = &self.session_id {
+            request = request.header("mcp-session-id", session_id);
+        }
+        if let Some(version) = &self.protocol_version {
+            request = request.header("mcp-protocol-version", version);
+        }
+        let response = request.send().await.context("request failed")?;
+        if let Some(session_id) = response.headers().get("mcp-session-id")
+            && let Ok(session_id) = session_id.to_str()
+        {
+            self.session_id = Some(session_id.to_string());
+        }
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("server returned {status}: {}", body.trim());
+        }
+        Ok(response)
+    }
+
+    async fn request(&mut self, id: u64, message: &Value) -> Result<Value> {
+        let response = self.post(message).await?;
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        let body = response.text().await.context("failed to read response")?;
+        if !is_stream {
+            return serde_json::from_str(&body).context("invalid JSON-RPC response");
+        }
+
+        let mut buffer = body.replace("\r\n", "\n");
+        buffer.push_str("\n\n");
+        while let Some(data) = get_event(&mut buffer) {
+            if let Ok(incoming) = serde_json::from_str::<Value>(&data)
+                && incoming.get("method").is_none()
+                && incoming["id"] == id
+            {
+                return Ok(incoming);
+            }
+        }
+        bail!("event stream ended without a response")
+    }
+}
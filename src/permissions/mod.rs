@@ -0,0 +1,305 @@
+//! Allow and deny rules for tool calls, from `permissions.allow` and
+//! `permissions.deny`.
+//!
+//! A rule is a tool name, optionally with a pattern: `bash(cargo test:*)`
+//! matches commands, `write_file(src/**)` matches paths. Deny rules win
+//! over allow rules; a call no rule matches is left to `tools.approval`.
+
+mod pattern;
+#[cfg(test)]
+mod permissions_tests;
+
+use crate::agent::ApprovalMode;
+use crate::config::{Config, Source};
+use crate::tools::{OutsideAccess, ToolRegistry, Workspace};
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::fmt;
+use std::path::PathBuf;
+
+pub(crate) use pattern::wildcard_matches;
+
+/// Tools whose patterns match the `command` argument.
+const COMMAND_TOOLS: &[&str] = &["bash"];
+
+/// Tools whose patterns match the `path` argument, which defaults to the
+/// working directory.
+const PATH_TOOLS: &[&str] = &["read_file", "write_file", "edit", "ls", "grep", "find"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub effect: Effect,
+    /// The rule as written, e.g. `bash(cargo test:*)`.
+    pub text: String,
+    pub source: Source,
+    tool: String,
+    pattern: Option<String>,
+}
+
+impl Rule {
+    fn parse(text: &str, effect: Effect, source: Source) -> Result<Self> {
+        let text = text.trim();
+        let (tool, pattern) = match text.split_once('(') {
+            Some((tool, rest)) => {
+                let Some(pattern) = rest.strip_suffix(')') else {
+                    bail!("rule '{text}' is missing its closing ')'");
+                };
+                if pattern.trim().is_empty() {
+                    bail!("rule '{text}' has an empty pattern; write '{tool}' to match every call");
+                }
+                (tool.trim(), Some(pattern.trim().to_string()))
+            }
+            None => (text, None),
+        };
+        let valid_name = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if tool.is_empty() || !tool.chars().all(valid_name) {
+            bail!("rule '{text}' does not start with a tool name");
+        }
+        if pattern.is_some() && !COMMAND_TOOLS.contains(&tool) && !PATH_TOOLS.contains(&tool) {
+            bail!("rule '{text}': only bash and the built-in file tools take a pattern");
+        }
+        Ok(Self {
+            effect,
+            text: text.to_string(),
+            source,
+            tool: tool.to_string(),
+            pattern,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.text, self.source)
+    }
+}
+
+/// What the rules say about one call.
+#[derive(Debug)]
+pub enum Decision<'a> {
+    /// Every command in the call matched one of these allow rules.
+    Allowed(Vec<&'a Rule>),
+    Denied(&'a Rule),
+    /// No rule applies; `tools.approval` decides.
+    Unmatched,
+}
+
+impl Decision<'_> {
+    /// The tool output for a denied call.
+    pub fn denial(&self, tool: &str) -> Option<String> {
+        match self {
+            Self::Denied(rule) => Some(format!(
+                "Error: this {tool} call is denied by the permission rule {rule}"
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// No rules by default, so every call is left to `tools.approval`.
+#[derive(Default)]
+pub struct Permissions {
+    rules: Vec<Rule>,
+    /// Relative path patterns and tool arguments are resolved against this.
+    root: PathBuf,
+}
+
+impl Permissions {
+    /// The rules of every config layer, relative to the working directory.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (key, effect) in [
+            ("permissions.deny", Effect::Deny),
+            ("permissions.allow", Effect::Allow),
+        ] {
+            for (text, source) in config.list(key) {
+                let rule = Rule::parse(text, effect, source.clone())
+                    .with_context(|| format!("invalid '{key}' in {source}"))?;
+                rules.push(rule);
+            }
+        }
+        let root = std::env::current_dir().context("failed to read the working directory")?;
+        Ok(Self { rules, root })
+    }
+
+    /// Deny rules first, then allow rules. A `bash` call is allowed only if
+    /// every command it runs matches an allow rule.
+    pub fn check(&self, tool: &str, arguments: &Value) -> Decision<'_> {
+        let rules = |effect| {
+            self.rules
+                .iter()
+                .filter(move |rule| rule.effect == effect && rule.tool == tool)
+        };
+        if let Some(rule) = rules(Effect::Deny).find(|rule| self.denies(rule, arguments)) {
+            return Decision::Denied(rule);
+        }
+
+        if !COMMAND_TOOLS.contains(&tool) {
+            return rules(Effect::Allow)
+                .find(|rule| self.path_rule_matches(rule, arguments))
+                .map_or(Decision::Unmatched, |rule| Decision::Allowed(vec![rule]));
+        }
+        if let Some(rule) = rules(Effect::Allow).find(|rule| rule.pattern.is_none()) {
+            return Decision::Allowed(vec![rule]);
+        }
+        let Some(command) = arguments["command"].as_str() else {
+            return Decision::Unmatched;
+        };
+        if pattern::has_substitution(command) {
+            return Decision::Unmatched;
+        }
+        let mut matched: Vec<&Rule> = Vec::new();
+        for simple in pattern::simple_commands(command) {
+            let rule = rules(Effect::Allow).find(|rule| {
+                rule.pattern
+                    .as_deref()
+                    .is_some_and(|p| pattern::allow_matches(p, simple))
+            });
+            match rule {
+                Some(rule) if !matched.iter().any(|seen| std::ptr::eq(*seen, rule)) => {
+                    matched.push(rule);
+                }
+                Some(_) => {}
+                None => return Decision::Unmatched,
+            }
+        }
+        if matched.is_empty() {
+            Decision::Unmatched
+        } else {
+            Decision::Allowed(matched)
+        }
+    }
+
+    /// A deny rule catches a `bash` call if any command it runs matches.
+    fn denies(&self, rule: &Rule, arguments: &Value) -> bool {
+        if !COMMAND_TOOLS.contains(&rule.tool.as_str()) {
+            return self.path_rule_matches(rule, arguments);
+        }
+        let Some(pattern) = &rule.pattern else {
+            return true;
+        };
+        arguments["command"].as_str().is_some_and(|command| {
+            pattern::simple_commands(command)
+                .into_iter()
+                .any(|simple| pattern::deny_matches(pattern, simple))
+        })
+    }
+
+    fn path_rule_matches(&self, rule: &Rule, arguments: &Value) -> bool {
+        let Some(pattern) = &rule.pattern else {
+            return true;
+        };
+        let path = arguments["path"].as_str().unwrap_or(".");
+        pattern::path_matches(rule.effect, pattern, &self.root, path)
+    }
+}
+
+/// The command a command tool such as `bash` runs, or `None` for other
+/// tools.
+pub fn command<'a>(tool: &str, arguments: &'a Value) -> Option<&'a str> {
+    if COMMAND_TOOLS.contains(&tool) {
+        arguments["command"].as_str()
+    } else {
+        None
+    }
+}
+
+/// The patterns an "always allow" answer grants for `command`.
+///
+/// There is one per program it runs, such as `cargo test:*`. `None` when no
+/// pattern would match the command, as with a substitution, a redirection
+/// or a leading assignment.
+pub fn command_grants(command: &str) -> Option<Vec<String>> {
+    if pattern::has_substitution(command) {
+        return None;
+    }
+    let mut patterns: Vec<String> = Vec::new();
+    for simple in pattern::simple_commands(command) {
+        let grant = pattern::grant_pattern(simple);
+        if !pattern::allow_matches(&grant, simple) {
+            return None;
+        }
+        if !patterns.contains(&grant) {
+            patterns.push(grant);
+        }
+    }
+    (!patterns.is_empty()).then_some(patterns)
+}
+
+/// Whether every command `command` runs matches one of `patterns`, the
+/// way allow rules are checked.
+pub fn commands_match(patterns: &[String], command: &str) -> bool {
+    let simple = pattern::simple_commands(command);
+    !pattern::has_substitution(command)
+        && !simple.is_empty()
+        && simple.iter().all(|simple| {
+            patterns
+                .iter()
+                .any(|pattern| pattern::allow_matches(pattern, simple))
+        })
+}
+
+/// `ox permissions check`: say which rule decides a call, or what happens
+/// when none does. `input` is the command for `bash`, the path for the
+/// file tools, or a JSON object of arguments.
+pub fn print_check(config: &Config, tool: &str, input: &str) -> Result<()> {
+    let permissions = Permissions::from_config(config)?;
+    let input = input.trim();
+    let arguments = if input.starts_with('{') {
+        serde_json::from_str(input).context("invalid JSON arguments")?
+    } else if COMMAND_TOOLS.contains(&tool) {
+        serde_json::json!({ "command": input })
+    } else if PATH_TOOLS.contains(&tool) && !input.is_empty() {
+        serde_json::json!({ "path": input })
+    } else {
+        serde_json::json!({})
+    };
+
+    let decision = permissions.check(tool, &arguments);
+    let path = ToolRegistry::builtin()
+        .get(tool)
+        .and_then(|tool| tool.path(&arguments.to_string()));
+    if let (false, Some(path)) = (matches!(decision, Decision::Denied(_)), path) {
+        let workspace = Workspace::from_config(config)?;
+        if let Err(resolved) = workspace.resolve(&path) {
+            let outcome = match workspace.outside_access() {
+                OutsideAccess::Deny => "is refused",
+                _ => "asks first",
+            };
+            let setting = config.string("workspace.outside").unwrap_or_default();
+            println!(
+                "{path} resolves to {}, outside the workspace; with workspace.outside = \"{setting}\" the call {outcome}",
+                resolved.display()
+            );
+            return Ok(());
+        }
+    }
+    match decision {
+        Decision::Denied(rule) => println!("denied by {rule}"),
+        Decision::Allowed(rules) => {
+            for rule in rules {
+                println!("allowed by {rule}");
+            }
+        }
+        Decision::Unmatched => {
+            let mode = ApprovalMode::from_config(config)?;
+            let read_only = ToolRegistry::builtin()
+                .get(tool)
+                .map(|tool| !tool.is_mutating());
+            let outcome = match (mode, read_only) {
+                (ApprovalMode::Auto, _) | (ApprovalMode::AutoRead, Some(true)) => "runs",
+                (ApprovalMode::Ask, _) | (ApprovalMode::AutoRead, Some(false)) => "asks first",
+                (ApprovalMode::AutoRead, None) => "asks first unless the tool is read-only",
+            };
+            let mode = config.string("tools.approval").unwrap_or_default();
+            println!("no rule matches; with tools.approval = \"{mode}\" the call {outcome}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+//! Matching for rule patterns: shell command prefixes for `bash`, path
+//! globs for the file tools.
+
+use super::Effect;
+use crate::tools::real_path;
+use std::path::{Component, Path, PathBuf};
+
+/// Whether an allow pattern matches one simple command. `cargo test:*`
+/// matches `cargo test` with any arguments; anything else must match the
+/// whole command. Leading `NAME=value` assignments (`LD_PRELOAD`, `PATH`,
+/// `RUSTC_WRAPPER`) and redirections change what an allowed command does,
+/// so a command with either is never allowed by a rule.
+pub(super) fn allow_matches(pattern: &str, command: &str) -> bool {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    if words.first().is_some_and(|word| is_assignment(word)) || command.contains(['>', '<']) {
+        return false;
+    }
+    prefix_matches(pattern, &words.join(" "), |pattern| {
+        pattern.split_whitespace().collect::<Vec<_>>().join(" ")
+    })
+}
+
+/// Whether a deny pattern matches one simple command, ignoring leading
+/// assignments so `FOO=1 rm -rf target` is still caught by `rm:*`.
+pub(super) fn deny_matches(pattern: &str, command: &str) -> bool {
+    prefix_matches(pattern, &strip_assignments(command), strip_assignments)
+}
+
+fn prefix_matches(pattern: &str, command: &str, normalize: impl Fn(&str) -> String) -> bool {
+    let Some(prefix) = pattern.strip_suffix(":*") else {
+        return command == normalize(pattern);
+    };
+    let prefix = normalize(prefix);
+    command == prefix
+        || command
+            .strip_prefix(&prefix)
+            .is_some_and(|rest| rest.starts_with(' '))
+}
+
+/// The pattern an "always allow" answer grants for one simple command: the
+/// program, plus its first argument when that looks like a subcommand, as
+/// in `cargo test:*` or `make:*`.
+pub(super) fn grant_pattern(command: &str) -> String {
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    match words.next() {
+        Some(sub) if is_subcommand(sub) => format!("{program} {sub}:*"),
+        _ => format!("{program}:*"),
+    }
+}
+
+fn is_subcommand(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_lowercase())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Split a command line into the simple commands it runs, cutting at
+/// `;`, `&`, `|`, newlines, subshell parentheses and command
+/// substitutions. Quotes are not understood, so this can only split too
+/// much, which makes allow rules less likely to match, never more.
+pub(super) fn simple_commands(command: &str) -> Vec<&str> {
+    command
+        .split([';', '&', '|', '\n', '(', ')', '`'])
+        .map(|part| part.trim().trim_end_matches('$').trim())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Whether the command runs other commands that a prefix rule can't see.
+pub(super) fn has_substitution(command: &str) -> bool {
+    command.contains("$(") || command.contains('`') || command.contains("<(")
+}
+
+/// Collapse whitespace and drop leading `NAME=value` assignments, so
+/// `FOO=1  rm x` is matched as `rm x`.
+fn strip_assignments(command: &str) -> String {
+    let words: Vec<&str> = command
+        .split_whitespace()
+        .skip_while(|word| is_assignment(word))
+        .collect();
+    words.join(" ")
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Whether a path glob matches `path`, a tool argument resolved against
+/// `root`. Relative globs match paths inside `root`; absolute ones (and
+/// `~/`) match the absolute path. `**` matches any number of directories,
+/// `*` and `?` match within one path segment.
+///
+/// The glob is tried on the path as written and on where it leads once
+/// symlinks are followed, as the tool will: a deny rule matches if either
+/// does, an allow rule only if both do.
+pub(super) fn path_matches(effect: Effect, pattern: &str, root: &Path, path: &str) -> bool {
+    let path = root.join(expand_home(path));
+    let pattern = expand_home(pattern);
+    let mut candidates = [
+        (normalize(&path), normalize(root)),
+        (real_path(&path), real_path(root)),
+    ]
+    .into_iter()
+    .map(|(path, root)| glob_matches_under(&pattern, &root, &path));
+    match effect {
+        Effect::Deny => candidates.any(|matched| matched),
+        Effect::Allow => candidates.all(|matched| matched),
+    }
+}
+
+fn glob_matches_under(pattern: &str, root: &Path, path: &Path) -> bool {
+    if Path::new(pattern).is_absolute() {
+        return glob_matches(pattern, &path.to_string_lossy());
+    }
+    path.strip_prefix(root)
+        .is_ok_and(|relative| glob_matches(pattern, &relative.to_string_lossy()))
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+/// Resolve `.` and `..` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    segments_match(&pattern, &path)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, path_rest)| {
+            segment_matches(first.as_bytes(), segment.as_bytes()) && segments_match(rest, path_rest)
+        }),
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of
+/// characters and `?` any one.
+pub fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    segment_matches(pattern.as_bytes(), text.as_bytes())
+}
+
+fn segment_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| segment_matches(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && segment_matches(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && segment_matches(rest, &text[1..]),
+    }
+}
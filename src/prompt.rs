@@ -1,3 +1,5 @@
+use crate::tools::ToolRegistry;
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -39,10 +41,25 @@ fn build_agents_context(cwd: &Path) -> String {
     }
 }
 
-pub fn build() -> String {
+/// One line per registered tool, using the first sentence of its
+/// description.
+fn tool_list(tools: &ToolRegistry) -> String {
+    let mut list = String::new();
+    for tool in tools.iter() {
+        let description = tool.description();
+        let summary = description
+            .split_once(". ")
+            .map_or(description, |(first, _)| first);
+        let _ = writeln!(list, "- {}: {}", tool.name(), summary.trim_end_matches('.'));
+    }
+    list
+}
+
+pub fn build(tools: &ToolRegistry) -> String {
     let cwd_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
     let cwd = cwd_path.to_string_lossy().to_string();
     let agents_context = build_agents_context(&cwd_path);
+    let tool_list = tool_list(tools);
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_or(0, |duration| duration.as_secs());
@@ -53,14 +70,7 @@ You are an expert coding assistant operating inside ox, a terminal-based coding
 You help users by reading files, executing commands, editing code, and writing new files.
 
 Available tools:
-- read_file: Read file contents
-- ls: List directory contents
-- write_file: Create or overwrite files
-- edit: Make surgical edits to files (find exact text and replace)
-- grep: Search file contents for patterns
-- find: Find files by name pattern
-- bash: Execute shell commands
-
+{tool_list}
 Guidelines:
 - Use read_file to examine files before editing
 - Use edit for precise changes (old_text must match exactly)
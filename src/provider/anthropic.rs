@@ -0,0 +1,314 @@
+use super::{Provider, ProviderEvent, Request, StreamError, StreamParser, Usage, unparsable};
+use crate::auth::AuthConfig;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const API_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// Stream error types worth retrying, the in-stream forms of 529, 500
+/// and 429.
+const RETRYABLE_ERRORS: &[&str] = &["overloaded_error", "api_error", "rate_limit_error"];
+
+/// The Anthropic Messages API.
+pub struct AnthropicProvider;
+
+impl Provider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Anthropic API"
+    }
+
+    fn endpoint(&self, auth: &AuthConfig) -> String {
+        auth.api_url("messages")
+    }
+
+    fn request_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("anthropic-version"),
+            HeaderValue::from_static(API_VERSION),
+        );
+        headers
+    }
+
+    fn build_request(&self, request: &Request<'_>) -> Value {
+        serde_json::json!({
+            "model": request.model,
+            "max_tokens": DEFAULT_MAX_TOKENS,
+            "system": request.instructions,
+            "messages": to_messages(request.history),
+            "tools": request.tools.iter().map(to_anthropic_tool).collect::<Vec<_>>(),
+            "stream": true
+        })
+    }
+
+    fn stream_parser(&self) -> Box<dyn StreamParser> {
+        Box::new(AnthropicParser::default())
+    }
+}
+
+fn to_anthropic_tool(tool: &Value) -> Value {
+    serde_json::json!({
+        "name": tool["name"],
+        "description": tool["description"],
+        "input_schema": tool["parameters"]
+    })
+}
+
+/// Translate Responses-style history into Messages API turns. Calls become
+/// `tool_use` blocks, outputs become `tool_result` blocks, and consecutive
+/// blocks from the same role are merged since roles must alternate.
+pub(super) fn to_messages(history: &[Value]) -> Vec<Value> {
+    let mut messages: Vec<Value> = Vec::new();
+    for entry in history {
+        let Some((role, block)) = to_block(entry) else {
+            continue;
+        };
+        match messages.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.push(block);
+                }
+            }
+            _ => messages.push(serde_json::json!({ "role": role, "content": [block] })),
+        }
+    }
+    messages
+}
+
+fn to_block(entry: &Value) -> Option<(&'static str, Value)> {
+    match entry["type"].as_str() {
+        Some("function_call") => {
+            let arguments = entry["arguments"].as_str().unwrap_or("");
+            let input = serde_json::from_str::<Value>(arguments)
+                .ok()
+                .filter(Value::is_object)
+                .unwrap_or_else(|| serde_json::json!({}));
+            Some((
+                "assistant",
+                serde_json::json!({
+                    "type": "tool_use",
+                    "id": entry["call_id"],
+                    "name": entry["name"],
+                    "input": input
+                }),
+            ))
+        }
+        Some("function_call_output") => Some((
+            "user",
+            serde_json::json!({
+                "type": "tool_result",
+                "tool_use_id": entry["call_id"],
+                "content": entry["output"]
+            }),
+        )),
+        _ => {
+            let role = if entry["role"] == "assistant" {
+                "assistant"
+            } else {
+                "user"
+            };
+            let text = entry["content"].as_str().unwrap_or("");
+            // The API rejects empty text blocks.
+            (!text.is_empty()).then(|| (role, serde_json::json!({ "type": "text", "text": text })))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum AnthropicEvent {
+    #[serde(rename = "content_block_start")]
+    ContentBlockStart { index: usize, content_block: Block },
+
+    #[serde(rename = "content_block_delta")]
+    ContentBlockDelta { index: usize, delta: BlockDelta },
+
+    #[serde(rename = "content_block_stop")]
+    ContentBlockStop { index: usize },
+
+    #[serde(rename = "message_start")]
+    MessageStart { message: MessageStart },
+
+    /// Carries the cumulative output token count for the message.
+    #[serde(rename = "message_delta")]
+    MessageDelta {
+        #[serde(default)]
+        usage: Option<Usage>,
+    },
+
+    #[serde(rename = "error")]
+    Error { error: ErrorBody },
+
+    #[serde(other)]
+    Ignored,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct MessageStart {
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Block {
+    #[serde(rename = "text")]
+    Text {
+        #[serde(default)]
+        text: String,
+    },
+
+    #[serde(rename = "tool_use")]
+    ToolUse { id: String, name: String },
+
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum BlockDelta {
+    #[serde(rename = "text_delta")]
+    Text { text: String },
+
+    #[serde(rename = "input_json_delta")]
+    InputJson { partial_json: String },
+
+    #[serde(other)]
+    Other,
+}
+
+enum OpenBlock {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input_json: String,
+    },
+}
+
+/// Tracks open content blocks by index and emits each one when it stops.
+#[derive(Default)]
+pub(super) struct AnthropicParser {
+    blocks: HashMap<usize, OpenBlock>,
+}
+
+impl StreamParser for AnthropicParser {
+    fn parse(&mut self, data: &str) -> Vec<ProviderEvent> {
+        let event: AnthropicEvent = match serde_json::from_str(data) {
+            Ok(event) => event,
+            Err(err) => return vec![unparsable(data, &err)],
+        };
+
+        match event {
+            AnthropicEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => match content_block {
+                Block::Text { text } => {
+                    let events = if text.is_empty() {
+                        Vec::new()
+                    } else {
+                        vec![ProviderEvent::TextDelta(text.clone())]
+                    };
+                    self.blocks.insert(index, OpenBlock::Text(text));
+                    events
+                }
+                Block::ToolUse { id, name } => {
+                    let started = ProviderEvent::ToolCallStarted { name: name.clone() };
+                    self.blocks.insert(
+                        index,
+                        OpenBlock::ToolUse {
+                            id,
+                            name,
+                            input_json: String::new(),
+                        },
+                    );
+                    vec![started]
+                }
+                Block::Other => Vec::new(),
+            },
+            AnthropicEvent::ContentBlockDelta { index, delta } => {
+                match (self.blocks.get_mut(&index), delta) {
+                    (Some(OpenBlock::Text(text)), BlockDelta::Text { text: delta }) => {
+                        text.push_str(&delta);
+                        vec![ProviderEvent::TextDelta(delta)]
+                    }
+                    (
+                        Some(OpenBlock::ToolUse { input_json, .. }),
+                        BlockDelta::InputJson { partial_json },
+                    ) => {
+                        input_json.push_str(&partial_json);
+                        Vec::new()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            AnthropicEvent::ContentBlockStop { index } => self
+                .blocks
+                .remove(&index)
+                .map(close_block)
+                .into_iter()
+                .collect(),
+            AnthropicEvent::MessageStart { message } => message
+                .usage
+                .map(|usage| {
+                    ProviderEvent::Usage(Usage {
+                        input_tokens: usage.input_tokens,
+                        output_tokens: 0,
+                    })
+                })
+                .into_iter()
+                .collect(),
+            AnthropicEvent::MessageDelta { usage } => usage
+                .map(|usage| {
+                    ProviderEvent::Usage(Usage {
+                        input_tokens: 0,
+                        output_tokens: usage.output_tokens,
+                    })
+                })
+                .into_iter()
+                .collect(),
+            AnthropicEvent::Error { error } => vec![ProviderEvent::Error(StreamError {
+                retryable: RETRYABLE_ERRORS.contains(&error.kind.as_str()),
+                message: format!("Anthropic stream error: {} [{}]", error.message, error.kind),
+            })],
+            AnthropicEvent::Ignored => Vec::new(),
+        }
+    }
+}
+
+fn close_block(block: OpenBlock) -> ProviderEvent {
+    match block {
+        OpenBlock::Text(text) => ProviderEvent::MessageDone { text },
+        OpenBlock::ToolUse {
+            id,
+            name,
+            input_json,
+        } => ProviderEvent::ToolCallDone {
+            call_id: id,
+            name,
+            // A tool with no arguments streams no input deltas at all.
+            arguments: if input_json.is_empty() {
+                "{}".to_string()
+            } else {
+                input_json
+            },
+        },
+    }
+}
@@ -0,0 +1,121 @@
+use super::ProviderEvent;
+use super::StreamError;
+use super::StreamParser;
+use super::Usage;
+use super::anthropic::{AnthropicParser, to_messages};
+use serde_json::json;
+
+#[test]
+fn parser_emits_text_and_tool_use_blocks() {
+    let mut parser = AnthropicParser::default();
+    let mut events = Vec::new();
+    for data in [
+        r#"{"type":"message_start","message":{"id":"msg_1"}}"#,
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Checking."}}"#,
+        r#"{"type":"content_block_stop","index":0}"#,
+        r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"read_file","input":{}}}"#,
+        r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"path\": "}}"#,
+        r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"a.rs\"}"}}"#,
+        r#"{"type":"content_block_stop","index":1}"#,
+        r#"{"type":"message_stop"}"#,
+    ] {
+        events.extend(parser.parse(data));
+    }
+
+    assert_eq!(
+        events,
+        vec![
+            ProviderEvent::TextDelta("Checking.".to_string()),
+            ProviderEvent::MessageDone {
+                text: "Checking.".to_string()
+            },
+            ProviderEvent::ToolCallStarted {
+                name: "read_file".to_string()
+            },
+            ProviderEvent::ToolCallDone {
+                call_id: "toolu_1".to_string(),
+                name: "read_file".to_string(),
+                arguments: r#"{"path": "a.rs"}"#.to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn parser_turns_error_events_into_stream_errors() {
+    let mut parser = AnthropicParser::default();
+
+    assert_eq!(
+        parser.parse(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+        ),
+        vec![ProviderEvent::Error(StreamError {
+            message: "Anthropic stream error: Overloaded [overloaded_error]".to_string(),
+            retryable: true,
+        })]
+    );
+    assert_eq!(
+        parser.parse(
+            r#"{"type":"error","error":{"type":"invalid_request_error","message":"Bad input"}}"#
+        ),
+        vec![ProviderEvent::Error(StreamError {
+            message: "Anthropic stream error: Bad input [invalid_request_error]".to_string(),
+            retryable: false,
+        })]
+    );
+}
+
+#[test]
+fn parser_reports_input_and_output_usage_separately() {
+    let mut parser = AnthropicParser::default();
+    let mut events = Vec::new();
+    for data in [
+        r#"{"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":120,"output_tokens":1}}}"#,
+        r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":42}}"#,
+    ] {
+        events.extend(parser.parse(data));
+    }
+
+    assert_eq!(
+        events,
+        vec![
+            ProviderEvent::Usage(Usage {
+                input_tokens: 120,
+                output_tokens: 0,
+            }),
+            ProviderEvent::Usage(Usage {
+                input_tokens: 0,
+                output_tokens: 42,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn to_messages_maps_calls_to_tool_use_and_tool_result() {
+    let history = vec![
+        json!({ "role": "user", "content": "read a.rs" }),
+        json!({ "role": "assistant", "content": "Reading." }),
+        json!({ "type": "function_call", "call_id": "c1", "name": "read_file", "arguments": "{\"path\":\"a.rs\"}" }),
+        json!({ "type": "function_call_output", "call_id": "c1", "output": "fn main() {}" }),
+        json!({ "role": "user", "content": "thanks" }),
+    ];
+
+    let messages = to_messages(&history);
+
+    assert_eq!(
+        messages,
+        vec![
+            json!({ "role": "user", "content": [{ "type": "text", "text": "read a.rs" }] }),
+            json!({ "role": "assistant", "content": [
+                { "type": "text", "text": "Reading." },
+                { "type": "tool_use", "id": "c1", "name": "read_file", "input": { "path": "a.rs" } }
+            ] }),
+            json!({ "role": "user", "content": [
+                { "type": "tool_result", "tool_use_id": "c1", "content": "fn main() {}" },
+                { "type": "text", "text": "thanks" }
+            ] }),
+        ]
+    );
+}
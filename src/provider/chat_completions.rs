@@ -0,0 +1,316 @@
+use super::{Provider, ProviderEvent, Request, StreamError, StreamParser, Usage, unparsable};
+use crate::auth::AuthConfig;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Error types and codes worth retrying: overload, server errors and rate
+/// limits reported in the stream.
+const RETRYABLE_ERRORS: &[&str] = &["server_error", "rate_limit_exceeded", "rate_limit_error"];
+
+/// Any server speaking the `/chat/completions` protocol: the hosted API or
+/// local model servers such as llama.cpp, vLLM and Ollama.
+pub struct ChatCompletionsProvider;
+
+impl Provider for ChatCompletionsProvider {
+    fn name(&self) -> &'static str {
+        "chat_completions"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Chat Completions API"
+    }
+
+    fn endpoint(&self, auth: &AuthConfig) -> String {
+        auth.api_url("chat/completions")
+    }
+
+    fn build_request(&self, request: &Request<'_>) -> Value {
+        let mut messages = vec![serde_json::json!({
+            "role": "system",
+            "content": request.instructions
+        })];
+        messages.extend(to_messages(request.history));
+
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": messages,
+            "tools": request.tools.iter().map(to_chat_tool).collect::<Vec<_>>(),
+            "stream": true,
+            "stream_options": { "include_usage": true }
+        });
+        if let Some(effort) = &request.reasoning.effort {
+            body["reasoning_effort"] = effort.clone().into();
+        }
+        body
+    }
+
+    fn stream_parser(&self) -> Box<dyn StreamParser> {
+        Box::new(ChatCompletionsParser::default())
+    }
+}
+
+fn to_chat_tool(tool: &Value) -> Value {
+    serde_json::json!({
+        "type": "function",
+        "function": {
+            "name": tool["name"],
+            "description": tool["description"],
+            "parameters": tool["parameters"],
+            "strict": tool["strict"]
+        }
+    })
+}
+
+/// Translate Responses-style history into chat messages. Consecutive
+/// `function_call` items (and the assistant text right before them) become
+/// one assistant message with `tool_calls`.
+pub(super) fn to_messages(history: &[Value]) -> Vec<Value> {
+    let mut messages: Vec<Value> = Vec::new();
+    for entry in history {
+        match entry["type"].as_str() {
+            Some("function_call") => {
+                let call = serde_json::json!({
+                    "id": entry["call_id"],
+                    "type": "function",
+                    "function": {
+                        "name": entry["name"],
+                        "arguments": entry["arguments"]
+                    }
+                });
+                match messages.last_mut() {
+                    Some(last) if last["role"] == "assistant" => {
+                        if let Some(calls) = last["tool_calls"].as_array_mut() {
+                            calls.push(call);
+                        } else {
+                            last["tool_calls"] = Value::Array(vec![call]);
+                        }
+                    }
+                    _ => messages.push(serde_json::json!({
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [call]
+                    })),
+                }
+            }
+            Some("function_call_output") => messages.push(serde_json::json!({
+                "role": "tool",
+                "tool_call_id": entry["call_id"],
+                "content": entry["output"]
+            })),
+            _ => messages.push(serde_json::json!({
+                "role": entry["role"],
+                "content": entry["content"]
+            })),
+        }
+    }
+    messages
+}
+
+#[derive(Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChatChoice>,
+    /// Only set on the final chunk, when `include_usage` was requested.
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+    /// Set instead of `choices` when the server fails mid-stream.
+    #[serde(default)]
+    error: Option<ChatError>,
+}
+
+#[derive(Deserialize)]
+struct ChatError {
+    #[serde(default)]
+    message: String,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    /// A string from the hosted API, a number from some local servers.
+    #[serde(default)]
+    code: Option<Value>,
+}
+
+impl ChatError {
+    fn into_event(self) -> ProviderEvent {
+        let code = match self.code {
+            Some(Value::String(code)) => Some(code),
+            Some(Value::Number(code)) => Some(code.to_string()),
+            _ => None,
+        };
+        let retryable = [&self.kind, &code]
+            .into_iter()
+            .flatten()
+            .any(|kind| RETRYABLE_ERRORS.contains(&kind.as_str()));
+        let kind = self.kind.or(code).unwrap_or_else(|| "unknown".to_string());
+        ProviderEvent::Error(StreamError {
+            message: format!("Chat Completions stream error: {} [{kind}]", self.message),
+            retryable,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    #[serde(default)]
+    delta: ChatDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ChatDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+#[derive(Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: Option<usize>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct FunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Accumulates streamed text and `tool_calls` deltas (keyed by `index`)
+/// and emits complete items once the choice finishes.
+pub(super) struct ChatCompletionsParser {
+    text: String,
+    tool_calls: BTreeMap<usize, PartialToolCall>,
+    finished: bool,
+    /// Starts the ids of calls the server sent without one. Random per
+    /// response, so ids never repeat across turns of a session.
+    fallback_id: String,
+}
+
+impl Default for ChatCompletionsParser {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            tool_calls: BTreeMap::new(),
+            finished: false,
+            fallback_id: format!("call_{:016x}", fastrand::u64(..)),
+        }
+    }
+}
+
+impl ChatCompletionsParser {
+    fn apply_tool_call_delta(
+        &mut self,
+        position: usize,
+        delta: ToolCallDelta,
+        events: &mut Vec<ProviderEvent>,
+    ) {
+        let index = delta.index.unwrap_or(position);
+        let call = self.tool_calls.entry(index).or_default();
+        if let Some(id) = delta.id.filter(|id| !id.is_empty()) {
+            call.id = id;
+        }
+        let Some(function) = delta.function else {
+            return;
+        };
+        if let Some(name) = function.name.filter(|name| !name.is_empty()) {
+            if call.name.is_empty() {
+                events.push(ProviderEvent::ToolCallStarted { name: name.clone() });
+            }
+            call.name = name;
+        }
+        if let Some(arguments) = function.arguments {
+            call.arguments.push_str(&arguments);
+        }
+    }
+
+    fn flush(&mut self) -> Vec<ProviderEvent> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+
+        let mut events = Vec::new();
+        let text = std::mem::take(&mut self.text);
+        if !text.is_empty() {
+            events.push(ProviderEvent::MessageDone { text });
+        }
+        for (index, call) in std::mem::take(&mut self.tool_calls) {
+            let call_id = if call.id.is_empty() {
+                format!("{}_{index}", self.fallback_id)
+            } else {
+                call.id
+            };
+            events.push(ProviderEvent::ToolCallDone {
+                call_id,
+                name: call.name,
+                arguments: call.arguments,
+            });
+        }
+        events
+    }
+}
+
+impl StreamParser for ChatCompletionsParser {
+    fn parse(&mut self, data: &str) -> Vec<ProviderEvent> {
+        if data.trim() == "[DONE]" {
+            return self.flush();
+        }
+        let chunk: ChatChunk = match serde_json::from_str(data) {
+            Ok(chunk) => chunk,
+            Err(err) => return vec![unparsable(data, &err)],
+        };
+        if let Some(error) = chunk.error {
+            return vec![error.into_event()];
+        }
+
+        let mut events = Vec::new();
+        let mut finished = false;
+        for choice in chunk.choices {
+            if let Some(content) = choice.delta.content.filter(|content| !content.is_empty()) {
+                self.text.push_str(&content);
+                events.push(ProviderEvent::TextDelta(content));
+            }
+            for (position, delta) in choice.delta.tool_calls.into_iter().enumerate() {
+                self.apply_tool_call_delta(position, delta, &mut events);
+            }
+            finished |= choice.finish_reason.is_some();
+        }
+        if finished {
+            events.extend(self.flush());
+        }
+        if let Some(usage) = chunk.usage {
+            events.push(ProviderEvent::Usage(Usage {
+                input_tokens: usage.prompt_tokens,
+                output_tokens: usage.completion_tokens,
+            }));
+        }
+        events
+    }
+
+    fn finish(&mut self) -> Vec<ProviderEvent> {
+        self.flush()
+    }
+}
@@ -0,0 +1,141 @@
+use super::ProviderEvent;
+use super::StreamError;
+use super::StreamParser;
+use super::Usage;
+use super::chat_completions::{ChatCompletionsParser, to_messages};
+use serde_json::json;
+
+#[test]
+fn parser_assembles_streamed_tool_call_deltas() {
+    let mut parser = ChatCompletionsParser::default();
+    let mut events = Vec::new();
+    for data in [
+        r#"{"choices":[{"delta":{"content":"Let me look."}}]}"#,
+        r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_a","type":"function","function":{"name":"read_file","arguments":""}}]}}]}"#,
+        r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"path\":"}}]}}]}"#,
+        r#"{"choices":[{"delta":{"tool_calls":[{"index":1,"id":"call_b","function":{"name":"ls","arguments":"{}"}}]}}]}"#,
+        r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"a.rs\"}"}}]}}]}"#,
+        r#"{"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+        "[DONE]",
+    ] {
+        events.extend(parser.parse(data));
+    }
+    events.extend(parser.finish());
+
+    assert_eq!(
+        events,
+        vec![
+            ProviderEvent::TextDelta("Let me look.".to_string()),
+            ProviderEvent::ToolCallStarted {
+                name: "read_file".to_string()
+            },
+            ProviderEvent::ToolCallStarted {
+                name: "ls".to_string()
+            },
+            ProviderEvent::MessageDone {
+                text: "Let me look.".to_string()
+            },
+            ProviderEvent::ToolCallDone {
+                call_id: "call_a".to_string(),
+                name: "read_file".to_string(),
+                arguments: r#"{"path":"a.rs"}"#.to_string(),
+            },
+            ProviderEvent::ToolCallDone {
+                call_id: "call_b".to_string(),
+                name: "ls".to_string(),
+                arguments: "{}".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn parser_flushes_text_when_stream_ends_without_finish_reason() {
+    let mut parser = ChatCompletionsParser::default();
+    parser.parse(r#"{"choices":[{"delta":{"content":"hi"}}]}"#);
+
+    assert_eq!(
+        parser.finish(),
+        vec![ProviderEvent::MessageDone {
+            text: "hi".to_string()
+        }]
+    );
+}
+
+#[test]
+fn parser_reports_usage_from_final_chunk() {
+    let mut parser = ChatCompletionsParser::default();
+
+    let events = parser.parse(
+        r#"{"choices":[],"usage":{"prompt_tokens":30,"completion_tokens":7,"total_tokens":37}}"#,
+    );
+
+    assert_eq!(
+        events,
+        vec![ProviderEvent::Usage(Usage {
+            input_tokens: 30,
+            output_tokens: 7,
+        })]
+    );
+}
+
+#[test]
+fn to_messages_groups_tool_calls_with_preceding_text() {
+    let history = vec![
+        json!({ "role": "user", "content": "list files" }),
+        json!({ "role": "assistant", "content": "Sure." }),
+        json!({ "type": "function_call", "call_id": "c1", "name": "ls", "arguments": "{}" }),
+        json!({ "type": "function_call", "call_id": "c2", "name": "find", "arguments": "{}" }),
+        json!({ "type": "function_call_output", "call_id": "c1", "output": "a.rs" }),
+        json!({ "type": "function_call_output", "call_id": "c2", "output": "b.rs" }),
+    ];
+
+    let messages = to_messages(&history);
+
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[1]["role"], "assistant");
+    assert_eq!(messages[1]["content"], "Sure.");
+    assert_eq!(messages[1]["tool_calls"][0]["id"], "c1");
+    assert_eq!(messages[1]["tool_calls"][1]["function"]["name"], "find");
+    assert_eq!(
+        messages[2],
+        json!({ "role": "tool", "tool_call_id": "c1", "content": "a.rs" })
+    );
+}
+
+#[test]
+fn parser_gives_calls_without_an_id_unique_ones() {
+    let call = r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"name":"ls","arguments":"{}"}}]},"finish_reason":"tool_calls"}]}"#;
+    let ids: Vec<String> = (0..2)
+        .flat_map(|_| ChatCompletionsParser::default().parse(call))
+        .filter_map(|event| match event {
+            ProviderEvent::ToolCallDone { call_id, .. } => Some(call_id),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(ids.len(), 2);
+    assert!(ids.iter().all(|id| id.starts_with("call_")), "{ids:?}");
+    assert_ne!(ids[0], ids[1]);
+}
+
+#[test]
+fn parser_turns_error_chunks_into_stream_errors() {
+    let mut parser = ChatCompletionsParser::default();
+
+    assert_eq!(
+        parser.parse(r#"{"error":{"message":"The server had an error","type":"server_error"}}"#),
+        vec![ProviderEvent::Error(StreamError {
+            message: "Chat Completions stream error: The server had an error [server_error]"
+                .to_string(),
+            retryable: true,
+        })]
+    );
+    assert_eq!(
+        parser.parse(r#"{"error":{"message":"context too long","code":400}}"#),
+        vec![ProviderEvent::Error(StreamError {
+            message: "Chat Completions stream error: context too long [400]".to_string(),
+            retryable: false,
+        })]
+    );
+}
@@ -0,0 +1,147 @@
+mod anthropic;
+#[cfg(test)]
+mod anthropic_tests;
+mod chat_completions;
+#[cfg(test)]
+mod chat_completions_tests;
+mod responses;
+mod responses_events;
+#[cfg(test)]
+mod responses_tests;
+
+use crate::auth::AuthConfig;
+use crate::config::Config;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use anthropic::AnthropicProvider;
+pub use chat_completions::ChatCompletionsProvider;
+pub use responses::ResponsesProvider;
+
+/// Provider-neutral stream events consumed by the agent loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProviderEvent {
+    TextDelta(String),
+    ToolCallStarted {
+        name: String,
+    },
+    MessageDone {
+        text: String,
+    },
+    ToolCallDone {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    Usage(Usage),
+    /// The API reported an error partway through the stream.
+    Error(StreamError),
+    /// Something in the stream was skipped, such as an unparsable event.
+    Warning(String),
+}
+
+/// The warning for an SSE payload that isn't the JSON expected.
+fn unparsable(data: &str, error: &serde_json::Error) -> ProviderEvent {
+    let preview: String = data.chars().take(200).collect();
+    ProviderEvent::Warning(format!(
+        "failed to parse SSE event JSON: {error}; payload: {preview}"
+    ))
+}
+
+/// An error event in a response stream. The stream ends with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamError {
+    pub message: String,
+    /// Whether the same request may succeed later, as with an overloaded
+    /// API.
+    pub retryable: bool,
+}
+
+impl std::fmt::Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// Token counts for one or more model calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Reasoning controls for models that support them. Providers without an
+/// equivalent ignore these.
+#[derive(Clone, Debug, Default)]
+pub struct Reasoning {
+    pub effort: Option<String>,
+    pub summary: Option<String>,
+}
+
+impl Reasoning {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            effort: config.string("reasoning.effort").map(str::to_string),
+            summary: config.string("reasoning.summary").map(str::to_string),
+        }
+    }
+}
+
+pub struct Request<'a> {
+    pub model: &'a str,
+    pub instructions: &'a str,
+    pub history: &'a [Value],
+    pub tools: &'a [Value],
+    pub reasoning: &'a Reasoning,
+}
+
+/// A model API backend. History and tool definitions are always kept in
+/// Responses API form; providers translate them on the way out.
+pub trait Provider: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// The API as named in messages to the user, e.g. `Anthropic API`.
+    fn display_name(&self) -> &'static str;
+    fn endpoint(&self, auth: &AuthConfig) -> String;
+
+    /// Protocol headers sent with every request, on top of auth headers.
+    fn request_headers(&self) -> HeaderMap {
+        HeaderMap::new()
+    }
+
+    fn build_request(&self, request: &Request<'_>) -> Value;
+    fn stream_parser(&self) -> Box<dyn StreamParser>;
+}
+
+pub trait StreamParser: Send {
+    /// Parse one SSE `data:` payload.
+    fn parse(&mut self, data: &str) -> Vec<ProviderEvent>;
+
+    /// Flush anything still buffered once the stream ends.
+    fn finish(&mut self) -> Vec<ProviderEvent> {
+        Vec::new()
+    }
+}
+
+/// Pick the provider from the `provider` setting (`responses`, `chat` or
+/// `anthropic`). With `auto`, Anthropic auth implies the Anthropic provider.
+pub fn from_config(config: &Config, auth: &AuthConfig) -> Box<dyn Provider> {
+    let kind = config.string("provider").unwrap_or_default();
+    match kind.to_ascii_lowercase().as_str() {
+        "anthropic" => Box::new(AnthropicProvider),
+        "chat" | "chat_completions" | "chat-completions" => Box::new(ChatCompletionsProvider),
+        "auto" if auth.is_anthropic() => Box::new(AnthropicProvider),
+        _ => Box::new(ResponsesProvider),
+    }
+}
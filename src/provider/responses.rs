@@ -0,0 +1,119 @@
+use super::responses_events::{OutputItem, ResponseError, StreamEvent};
+use super::{Provider, ProviderEvent, Reasoning, Request, StreamError, StreamParser, unparsable};
+use crate::auth::AuthConfig;
+use serde_json::Value;
+
+/// Error codes worth retrying: server errors and rate limits reported in
+/// the stream.
+const RETRYABLE_ERRORS: &[&str] = &["server_error", "rate_limit_exceeded"];
+
+/// The Responses API, used by both API key and subscription auth.
+pub struct ResponsesProvider;
+
+impl Provider for ResponsesProvider {
+    fn name(&self) -> &'static str {
+        "responses"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Responses API"
+    }
+
+    fn endpoint(&self, auth: &AuthConfig) -> String {
+        auth.api_url("responses")
+    }
+
+    fn build_request(&self, request: &Request<'_>) -> Value {
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "store": false,
+            "instructions": request.instructions,
+            "input": request.history,
+            "tools": request.tools,
+            "stream": true
+        });
+        let Reasoning { effort, summary } = request.reasoning;
+        if effort.is_some() || summary.is_some() {
+            let mut reasoning = serde_json::Map::new();
+            if let Some(effort) = effort {
+                reasoning.insert("effort".to_string(), effort.clone().into());
+            }
+            if let Some(summary) = summary {
+                reasoning.insert("summary".to_string(), summary.clone().into());
+            }
+            body["reasoning"] = Value::Object(reasoning);
+        }
+        body
+    }
+
+    fn stream_parser(&self) -> Box<dyn StreamParser> {
+        Box::new(ResponsesParser)
+    }
+}
+
+pub(super) struct ResponsesParser;
+
+impl StreamParser for ResponsesParser {
+    fn parse(&mut self, data: &str) -> Vec<ProviderEvent> {
+        let event = match parse_event(data) {
+            Ok(event) => event,
+            Err(err) => return vec![unparsable(data, &err)],
+        };
+        match event {
+            StreamEvent::OutputItemAdded { item } if item.item_type == "function_call" => {
+                vec![ProviderEvent::ToolCallStarted {
+                    name: item.name.unwrap_or_else(|| "unknown".to_string()),
+                }]
+            }
+            StreamEvent::TextDelta { delta } => vec![ProviderEvent::TextDelta(delta)],
+            StreamEvent::OutputItemDone { item } => output_item_done(item).into_iter().collect(),
+            StreamEvent::Completed { response } => response
+                .usage
+                .map(ProviderEvent::Usage)
+                .into_iter()
+                .collect(),
+            StreamEvent::Failed { response } => {
+                vec![stream_error(response.error.unwrap_or_else(|| {
+                    ResponseError {
+                        code: None,
+                        message: "the response failed".to_string(),
+                    }
+                }))]
+            }
+            StreamEvent::Error(error) => vec![stream_error(error)],
+            StreamEvent::OutputItemAdded { .. } | StreamEvent::Ignored => Vec::new(),
+        }
+    }
+}
+
+fn output_item_done(item: OutputItem) -> Option<ProviderEvent> {
+    match item.item_type.as_str() {
+        "message" => {
+            let text = item
+                .content
+                .into_iter()
+                .next()
+                .and_then(|part| part.text)
+                .unwrap_or_default();
+            Some(ProviderEvent::MessageDone { text })
+        }
+        "function_call" => Some(ProviderEvent::ToolCallDone {
+            call_id: item.call_id.unwrap_or_default(),
+            name: item.name.unwrap_or_default(),
+            arguments: item.arguments.unwrap_or_default(),
+        }),
+        _ => None,
+    }
+}
+
+fn stream_error(error: ResponseError) -> ProviderEvent {
+    let code = error.code.unwrap_or_else(|| "unknown".to_string());
+    ProviderEvent::Error(StreamError {
+        retryable: RETRYABLE_ERRORS.contains(&code.as_str()),
+        message: format!("Responses stream error: {} [{code}]", error.message),
+    })
+}
+
+pub(super) fn parse_event(data: &str) -> serde_json::Result<StreamEvent> {
+    serde_json::from_str(data)
+}
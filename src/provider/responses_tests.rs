@@ -0,0 +1,93 @@
+use super::ProviderEvent;
+use super::StreamError;
+use super::StreamParser;
+use super::Usage;
+use super::responses::{ResponsesParser, parse_event};
+use super::responses_events::StreamEvent;
+
+#[test]
+fn parser_warns_about_invalid_json() {
+    assert!(parse_event("{not-json").is_err());
+
+    let events = ResponsesParser.parse("{not-json");
+    assert!(
+        matches!(&events[..], [ProviderEvent::Warning(warning)]
+            if warning.starts_with("failed to parse SSE event JSON: ") && warning.ends_with("payload: {not-json")),
+        "{events:?}"
+    );
+}
+
+#[test]
+fn parse_event_accepts_known_event() {
+    let data = r#"{"type":"response.output_text.delta","delta":"hi"}"#;
+    let event = parse_event(data).expect("event should parse");
+    assert!(matches!(event, StreamEvent::TextDelta { .. }));
+}
+
+#[test]
+fn parser_maps_function_call_items() {
+    let mut parser = ResponsesParser;
+
+    let added = parser.parse(
+        r#"{"type":"response.output_item.added","item":{"type":"function_call","name":"ls"}}"#,
+    );
+    let done = parser.parse(
+        r#"{"type":"response.output_item.done","item":{"type":"function_call","name":"ls","call_id":"c1","arguments":"{}"}}"#,
+    );
+
+    assert_eq!(
+        added,
+        vec![ProviderEvent::ToolCallStarted {
+            name: "ls".to_string()
+        }]
+    );
+    assert_eq!(
+        done,
+        vec![ProviderEvent::ToolCallDone {
+            call_id: "c1".to_string(),
+            name: "ls".to_string(),
+            arguments: "{}".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn parser_reports_usage_on_completion() {
+    let mut parser = ResponsesParser;
+
+    let events = parser.parse(
+        r#"{"type":"response.completed","response":{"id":"resp_1","usage":{"input_tokens":10,"output_tokens":5,"total_tokens":15}}}"#,
+    );
+
+    assert_eq!(
+        events,
+        vec![ProviderEvent::Usage(Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+        })]
+    );
+}
+
+#[test]
+fn parser_turns_failures_into_stream_errors() {
+    let mut parser = ResponsesParser;
+
+    assert_eq!(
+        parser.parse(
+            r#"{"type":"response.failed","response":{"id":"resp_1","status":"failed","error":{"code":"server_error","message":"The model failed"}}}"#,
+        ),
+        vec![ProviderEvent::Error(StreamError {
+            message: "Responses stream error: The model failed [server_error]".to_string(),
+            retryable: true,
+        })]
+    );
+    assert_eq!(
+        parser.parse(
+            r#"{"type":"error","code":"invalid_prompt","message":"bad input","param":null}"#
+        ),
+        vec![ProviderEvent::Error(StreamError {
+            message: "Responses stream error: bad input [invalid_prompt]".to_string(),
+            retryable: false,
+        })]
+    );
+}
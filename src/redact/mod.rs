@@ -0,0 +1,296 @@
+//! Redaction of secrets from tool output before it reaches the history,
+//! the session file and the model.
+//!
+//! Built-in detectors catch private keys, provider API keys, cloud and
+//! GitHub tokens, JWTs and long high-entropy strings; `redact.patterns`
+//! adds regexes of its own. Each secret is replaced by a placeholder
+//! derived from its hash, so the same value always gets the same one.
+//! The redactor remembers what each placeholder stands for, and mutating
+//! tools get the real values back in their arguments, so editing redacted
+//! text never writes a placeholder to disk.
+
+#[cfg(test)]
+mod redact_tests;
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Mutex, PoisonError};
+
+/// Built-in detectors, most specific first: PEM blocks before the tokens
+/// inside them. A capture group, if present, is the part replaced.
+const DETECTORS: &[(&str, &str)] = &[
+    (
+        "private-key",
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    ),
+    ("anthropic-key", r"\bsk-ant-[A-Za-z0-9_-]{20,}"),
+    (
+        "openai-key",
+        r"\bsk-(?:proj-|svcacct-|admin-)?[A-Za-z0-9_-]{20,}",
+    ),
+    (
+        "github-token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})",
+    ),
+    ("aws-access-key-id", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "aws-secret-access-key",
+        r#"(?i)aws_?secret_?access_?key["']?\s*[:=]\s*["']?([A-Za-z0-9/+=]{40})"#,
+    ),
+    (
+        "jwt",
+        r"\beyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]*",
+    ),
+];
+
+/// Candidates for the entropy check: long runs of base64 or token
+/// characters, with any base64 padding.
+const HIGH_ENTROPY_CANDIDATE: &str = r"[A-Za-z0-9+/_-]{32,}={0,2}";
+
+/// Bits per character above which a candidate counts as random. Hex
+/// digests such as commit hashes stay below it.
+const MIN_ENTROPY: f64 = 4.2;
+
+/// A placeholder as written by [`placeholder`].
+const PLACEHOLDER: &str = r"\[REDACTED:[a-z-]+:[0-9a-f]{8}\]";
+
+struct Detector {
+    name: String,
+    regex: Regex,
+}
+
+/// Replaces secrets in tool output, from `redact.enabled` and
+/// `redact.patterns`.
+pub struct Redactor {
+    detectors: Vec<Detector>,
+    high_entropy: Option<Regex>,
+    placeholders: Regex,
+    /// The value behind every placeholder handed out so far.
+    secrets: Mutex<HashMap<String, String>>,
+}
+
+/// What one pass found.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Redactions {
+    /// How many values were replaced, by detector.
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl Redactions {
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// A note for the model, or `None` if nothing was redacted.
+    pub fn note(&self) -> Option<String> {
+        if self.counts.is_empty() {
+            return None;
+        }
+        let kinds: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(name, count)| format!("{count} {name}"))
+            .collect();
+        Some(format!(
+            "Note: {} value(s) that looked like secrets were replaced with [REDACTED:...] placeholders ({}). The placeholders are not the real values.",
+            self.total(),
+            kinds.join(", ")
+        ))
+    }
+}
+
+/// The built-in detectors.
+impl Default for Redactor {
+    fn default() -> Self {
+        let detectors = DETECTORS
+            .iter()
+            .map(|(name, pattern)| Detector {
+                name: (*name).to_string(),
+                regex: Regex::new(pattern).expect("built-in detector is a valid regex"),
+            })
+            .collect();
+        Self {
+            detectors,
+            high_entropy: Some(
+                Regex::new(HIGH_ENTROPY_CANDIDATE).expect("built-in detector is a valid regex"),
+            ),
+            placeholders: Regex::new(PLACEHOLDER).expect("placeholder pattern is a valid regex"),
+            secrets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Redactor {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        if !config.bool("redact.enabled") {
+            return Ok(Self {
+                detectors: Vec::new(),
+                high_entropy: None,
+                ..Self::default()
+            });
+        }
+        let mut redactor = Self::default();
+        for (pattern, source) in config.list("redact.patterns") {
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("invalid 'redact.patterns' in {source}"))?;
+            redactor.detectors.push(Detector {
+                name: "custom".to_string(),
+                regex,
+            });
+        }
+        Ok(redactor)
+    }
+
+    /// Replace every secret in `text` with its placeholder.
+    pub fn redact(&self, text: &str) -> (String, Redactions) {
+        let mut redactions = Redactions::default();
+        let mut text = text.to_string();
+        for detector in &self.detectors {
+            let mut count = 0;
+            let replaced = detector.regex.replace_all(&text, |captures: &Captures| {
+                count += 1;
+                self.replace_secret(captures, &detector.name)
+            });
+            if count > 0 {
+                text = replaced.into_owned();
+                *redactions.counts.entry(detector.name.clone()).or_default() += count;
+            }
+        }
+        if let Some(candidates) = &self.high_entropy {
+            let mut count = 0;
+            let replaced = candidates.replace_all(&text, |captures: &Captures| {
+                let candidate = &captures[0];
+                if looks_random(candidate) {
+                    count += 1;
+                    self.remember("high-entropy", candidate)
+                } else {
+                    candidate.to_string()
+                }
+            });
+            if count > 0 {
+                text = replaced.into_owned();
+                redactions.counts.insert("high-entropy".to_string(), count);
+            }
+        }
+        (text, redactions)
+    }
+
+    /// Redact tool output, noting for the model what was replaced. Also
+    /// returns how many values were.
+    pub fn redact_output(&self, output: &str) -> (String, usize) {
+        let (output, redactions) = self.redact(output);
+        match redactions.note() {
+            Some(note) => (format!("{note}\n{output}"), redactions.total()),
+            None => (output, 0),
+        }
+    }
+
+    /// Put the real values back for the placeholders in the string values
+    /// of JSON `arguments`. Placeholders this redactor never handed out
+    /// are left alone.
+    pub fn restore(&self, arguments: &str) -> String {
+        if !self.placeholders.is_match(arguments) {
+            return arguments.to_string();
+        }
+        let Ok(mut value) = serde_json::from_str::<Value>(arguments) else {
+            return arguments.to_string();
+        };
+        let secrets = self.secrets.lock().unwrap_or_else(PoisonError::into_inner);
+        self.restore_strings(&mut value, &secrets);
+        value.to_string()
+    }
+
+    fn restore_strings(&self, value: &mut Value, secrets: &HashMap<String, String>) {
+        match value {
+            Value::String(text) => {
+                let restored = self.placeholders.replace_all(text, |captures: &Captures| {
+                    secrets
+                        .get(&captures[0])
+                        .cloned()
+                        .unwrap_or_else(|| captures[0].to_string())
+                });
+                *text = restored.into_owned();
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.restore_strings(item, secrets);
+                }
+            }
+            Value::Object(fields) => {
+                for field in fields.values_mut() {
+                    self.restore_strings(field, secrets);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The match with its first capture group, or all of it, replaced.
+    fn replace_secret(&self, captures: &Captures, name: &str) -> String {
+        let whole = captures.get(0).expect("group 0 always matches");
+        let Some(secret) = captures.get(1) else {
+            return self.remember(name, whole.as_str());
+        };
+        let text = whole.as_str();
+        let start = secret.start() - whole.start();
+        let end = secret.end() - whole.start();
+        format!(
+            "{}{}{}",
+            &text[..start],
+            self.remember(name, secret.as_str()),
+            &text[end..]
+        )
+    }
+
+    /// The placeholder for `secret`, recorded so [`Self::restore`] can
+    /// undo it.
+    fn remember(&self, name: &str, secret: &str) -> String {
+        let placeholder = placeholder(name, secret);
+        self.secrets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(placeholder.clone(), secret.to_string());
+        placeholder
+    }
+}
+
+/// `[REDACTED:<name>:<hash>]`, the same for the same secret every time.
+fn placeholder(name: &str, secret: &str) -> String {
+    let digest = Sha256::digest(secret.as_bytes());
+    let mut hash = String::with_capacity(8);
+    for byte in &digest[..4] {
+        let _ = write!(hash, "{byte:02x}");
+    }
+    format!("[REDACTED:{name}:{hash}]")
+}
+
+/// Mixed-case letters and digits with high Shannon entropy, as in random
+/// keys; identifiers, paths and hex digests don't qualify.
+fn looks_random(candidate: &str) -> bool {
+    let has = |check: fn(&char) -> bool| candidate.chars().any(|c| check(&c));
+    if !has(char::is_ascii_uppercase)
+        || !has(char::is_ascii_lowercase)
+        || !has(char::is_ascii_digit)
+    {
+        return false;
+    }
+    let mut frequencies = [0_u32; 256];
+    for byte in candidate.bytes() {
+        frequencies[usize::from(byte)] += 1;
+    }
+    let length: u32 = frequencies.iter().sum();
+    let entropy: f64 = frequencies
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = f64::from(count) / f64::from(length);
+            -p * p.log2()
+        })
+        .sum();
+    entropy > MIN_ENTROPY
+}
@@ -0,0 +1,97 @@
+use super::Sandbox;
+use landlock::{
+    ABI, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetError, RulesetStatus,
+    path_beneath_rules,
+};
+use std::ffi::CStr;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/// The newest Landlock ABI we ask for; older kernels enforce what they
+/// support.
+const LANDLOCK_ABI: ABI = ABI::V5;
+
+/// Set up `command` to start in new namespaces under a Landlock ruleset
+/// that allows reading everywhere and writing only to the writable paths.
+///
+/// Everything that allocates happens here, before the fork; the child
+/// only makes system calls.
+pub(super) fn apply(sandbox: &Sandbox, command: &mut Command) -> Result<(), RulesetError> {
+    let writable = sandbox.writable_paths();
+    let ruleset = Ruleset::default()
+        .handle_access(AccessFs::from_all(LANDLOCK_ABI))?
+        .create()?
+        .add_rules(path_beneath_rules(["/"], AccessFs::from_read(LANDLOCK_ABI)))?
+        .add_rules(path_beneath_rules(
+            &writable,
+            AccessFs::from_all(LANDLOCK_ABI),
+        ))?;
+    let mut ruleset = Some(ruleset);
+
+    // SAFETY: `getuid` and `getgid` always succeed.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let uid_map = format!("{uid} {uid} 1");
+    let gid_map = format!("{gid} {gid} 1");
+    let mut namespaces = libc::CLONE_NEWUSER | libc::CLONE_NEWNS;
+    if !sandbox.network {
+        namespaces |= libc::CLONE_NEWNET;
+    }
+
+    let enter = move || -> io::Result<()> {
+        // SAFETY: plain system calls on values prepared before the fork.
+        unsafe {
+            if libc::unshare(namespaces) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            write_proc(c"/proc/self/setgroups", b"deny")?;
+            write_proc(c"/proc/self/uid_map", uid_map.as_bytes())?;
+            write_proc(c"/proc/self/gid_map", gid_map.as_bytes())?;
+            // Keep any mounts the command makes out of the parent namespace.
+            let flags = libc::MS_REC | libc::MS_PRIVATE;
+            let private = libc::mount(
+                std::ptr::null(),
+                c"/".as_ptr(),
+                std::ptr::null(),
+                flags,
+                std::ptr::null(),
+            );
+            if private != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let ruleset = ruleset
+            .take()
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
+        match ruleset.restrict_self() {
+            Ok(status) if status.ruleset != RulesetStatus::NotEnforced => Ok(()),
+            // The kernel has no Landlock: refuse rather than run unconfined.
+            _ => Err(io::Error::from_raw_os_error(libc::ENOSYS)),
+        }
+    };
+    // SAFETY: `enter` runs in the forked child before exec and does not
+    // allocate or take locks.
+    unsafe {
+        command.pre_exec(enter);
+    }
+    Ok(())
+}
+
+/// Write `contents` to a `/proc/self` file without allocating.
+///
+/// # Safety
+///
+/// Only system calls; safe to call between fork and exec.
+unsafe fn write_proc(path: &CStr, contents: &[u8]) -> io::Result<()> {
+    // SAFETY: `path` is NUL-terminated and `contents` outlives the calls.
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        let error = io::Error::last_os_error();
+        libc::close(fd);
+        if written < 0 { Err(error) } else { Ok(()) }
+    }
+}
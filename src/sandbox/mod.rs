@@ -0,0 +1,171 @@
+//! Opt-in sandbox for the commands the `bash` tool runs, from
+//! `sandbox.mode`.
+//!
+//! On Linux a sandboxed command gets its own user and mount namespaces,
+//! plus a network namespace unless `sandbox.network` is set, and Landlock
+//! limits writes to the workspace, the temp dirs and
+//! `sandbox.writable_roots`. Other platforms refuse to run commands with
+//! the sandbox on rather than run them unconfined.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(all(test, target_os = "linux"))]
+mod sandbox_tests;
+
+use crate::config::Config;
+use crate::tools::Workspace;
+use anyhow::{Result, bail};
+use std::path::PathBuf;
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SandboxMode {
+    /// Commands run with the user's full privileges.
+    #[default]
+    Off,
+    /// Commands may write to the workspace and the temp dirs.
+    WorkspaceWrite,
+    /// Commands may not write anywhere.
+    ReadOnly,
+}
+
+impl SandboxMode {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::WorkspaceWrite => "workspace-write",
+            Self::ReadOnly => "read-only",
+        }
+    }
+}
+
+/// Devices every sandboxed command may write to.
+const DEVICES: &[&str] = &["/dev/null", "/dev/zero", "/dev/full"];
+
+#[derive(Clone, Debug, Default)]
+pub struct Sandbox {
+    pub mode: SandboxMode,
+    /// Whether sandboxed commands may use the network.
+    pub network: bool,
+    /// Writable in `workspace-write` mode.
+    pub workspace: PathBuf,
+    /// More writable paths for `workspace-write` mode, such as a cache.
+    /// Relative ones are resolved against the workspace.
+    pub writable_roots: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// The configured sandbox, with the same workspace root as the file
+    /// tools.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mode = match config.string("sandbox.mode").unwrap_or_default() {
+            "off" => SandboxMode::Off,
+            "workspace-write" => SandboxMode::WorkspaceWrite,
+            "read-only" => SandboxMode::ReadOnly,
+            other => {
+                bail!("unknown sandbox.mode '{other}' (expected off, workspace-write or read-only)")
+            }
+        };
+        let workspace = Workspace::default_root()?;
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let writable_roots = config
+            .list("sandbox.writable_roots")
+            .map(|(path, _)| match (path.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => workspace.join(path),
+            })
+            .collect();
+        Ok(Self {
+            mode,
+            network: config.bool("sandbox.network"),
+            workspace,
+            writable_roots,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != SandboxMode::Off
+    }
+
+    /// Paths a sandboxed command may write to. Missing ones are left out,
+    /// since Landlock can only add rules for paths that exist.
+    fn writable_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = DEVICES.iter().map(PathBuf::from).collect();
+        if self.mode == SandboxMode::WorkspaceWrite {
+            paths.push(self.workspace.clone());
+            paths.push(std::env::temp_dir());
+            paths.extend(["/tmp", "/var/tmp"].map(PathBuf::from));
+            paths.extend(self.writable_roots.iter().cloned());
+        }
+        paths.retain(|path| path.exists());
+        paths
+    }
+
+    /// Make `command` run inside the sandbox. Does nothing when it is off.
+    pub(crate) fn apply(&self, command: &mut Command) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        #[cfg(target_os = "linux")]
+        return linux::apply(self, command).map_err(|e| self.setup_error(&e));
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = command;
+            Err(format!(
+                "Error: sandbox.mode = \"{}\" is only supported on Linux; set sandbox.mode = \"off\" to run commands unsandboxed",
+                self.mode.name()
+            ))
+        }
+    }
+
+    /// The error for a command that could not be started in the sandbox.
+    pub(crate) fn setup_error(&self, error: &dyn std::fmt::Display) -> String {
+        format!(
+            "Error: could not start the command in the sandbox (sandbox.mode = \"{}\"): {error}. \
+             The sandbox needs unprivileged user namespaces and Landlock (Linux 5.13 or later); \
+             set sandbox.mode = \"off\" to run commands unsandboxed",
+            self.mode.name()
+        )
+    }
+
+    /// A note for a failed command whose output looks like the sandbox
+    /// stopped it, so the model doesn't keep retrying.
+    pub(crate) fn explain_failure(&self, output: &str) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let lower = output.to_ascii_lowercase();
+        let blocked_write = [
+            "permission denied",
+            "read-only file system",
+            "operation not permitted",
+        ]
+        .iter()
+        .any(|needle| lower.contains(needle));
+        let blocked_network = !self.network
+            && [
+                "network is unreachable",
+                "could not resolve host",
+                "temporary failure in name resolution",
+                "name or service not known",
+            ]
+            .iter()
+            .any(|needle| lower.contains(needle));
+        if !blocked_write && !blocked_network {
+            return None;
+        }
+        let writable = match self.mode {
+            SandboxMode::WorkspaceWrite => format!(
+                "only {}, the temp dirs and sandbox.writable_roots are writable",
+                self.workspace.display()
+            ),
+            _ => "nothing is writable".to_string(),
+        };
+        let network = if self.network { "allowed" } else { "off" };
+        Some(format!(
+            "Note: the command ran in the sandbox (sandbox.mode = \"{}\"): {writable} and network access is {network}. \
+             This failure may be the sandbox blocking it; ask the user if the command needs more access.",
+            self.mode.name()
+        ))
+    }
+}
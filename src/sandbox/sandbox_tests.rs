@@ -0,0 +1,99 @@
+use super::{Sandbox, SandboxMode};
+use crate::tools::{ToolContext, ToolRegistry};
+use std::path::{Path, PathBuf};
+
+fn bash(sandbox: &Sandbox, command: &str) -> String {
+    let ctx = ToolContext {
+        sandbox: sandbox.clone(),
+        ..ToolContext::default()
+    };
+    let arguments = serde_json::json!({ "command": command }).to_string();
+    ToolRegistry::builtin()
+        .get("bash")
+        .expect("bash is built in")
+        .call(&arguments, &ctx)
+        .unwrap_or_else(|error| error)
+}
+
+fn sandbox(mode: SandboxMode, workspace: &Path) -> Sandbox {
+    Sandbox {
+        mode,
+        workspace: workspace.to_path_buf(),
+        ..Sandbox::default()
+    }
+}
+
+/// A fresh directory under `target/`, outside the temp dirs the sandbox
+/// always lets commands write to.
+fn scratch_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("target/sandbox-tests")
+        .join(format!("{:016x}", fastrand::u64(..)));
+    std::fs::create_dir_all(&dir).expect("create scratch dir");
+    dir
+}
+
+/// Whether this machine can run the sandbox; CI containers often can't.
+fn supported(output: &str) -> bool {
+    if output.contains("could not start the command in the sandbox") {
+        eprintln!("skipping: {output}");
+        return false;
+    }
+    true
+}
+
+#[test]
+fn workspace_write_confines_writes_to_the_workspace() {
+    let workspace = scratch_dir();
+    let outside = scratch_dir();
+    let sandbox = sandbox(SandboxMode::WorkspaceWrite, &workspace);
+
+    let inside = workspace.join("inside.txt");
+    let output = bash(&sandbox, &format!("echo hi > {}", inside.display()));
+    if !supported(&output) {
+        return;
+    }
+    assert!(!output.starts_with("Error"), "{output}");
+    assert!(inside.exists());
+
+    let blocked = outside.join("blocked.txt");
+    let output = bash(&sandbox, &format!("echo hi > {}", blocked.display()));
+    assert!(
+        output.starts_with("Error: command exited with code"),
+        "{output}"
+    );
+    assert!(
+        output.contains("Note: the command ran in the sandbox"),
+        "{output}"
+    );
+    assert!(!blocked.exists());
+
+    let output = bash(&sandbox, "cat /etc/hostname > /dev/null && echo read");
+    assert_eq!(output.trim(), "read");
+
+    let _ = std::fs::remove_dir_all(workspace);
+    let _ = std::fs::remove_dir_all(outside);
+}
+
+#[test]
+fn read_only_blocks_every_write_and_the_network() {
+    let workspace = scratch_dir();
+    let sandbox = sandbox(SandboxMode::ReadOnly, &workspace);
+
+    let file = workspace.join("file");
+    let output = bash(&sandbox, &format!("touch {}", file.display()));
+    if !supported(&output) {
+        return;
+    }
+    assert!(
+        output.contains("Note: the command ran in the sandbox"),
+        "{output}"
+    );
+    assert!(!file.exists());
+
+    // The new network namespace has nothing but loopback.
+    let output = bash(&sandbox, "tail -n +3 /proc/net/dev | cut -d: -f1");
+    assert_eq!(output.trim(), "lo");
+
+    let _ = std::fs::remove_dir_all(workspace);
+}
@@ -1,33 +1,63 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use serde_json::Value;
-use std::io::Write;
-use std::path::PathBuf;
+use std::sync::Arc;
 
-use super::store;
+use super::{SessionStore, repair};
 
+/// The in-memory history of one session, written through to its store.
 pub struct SessionManager {
     session_name: String,
-    path: PathBuf,
+    store: Option<Arc<dyn SessionStore>>,
     history: Vec<Value>,
+    /// Warnings from opening the session, reported with the next turn.
+    warnings: Vec<String>,
 }
 
 impl SessionManager {
-    pub fn open(session_name: &str) -> Result<Self> {
-        let path = store::session_path(session_name)?;
-        let history = store::load_history_file(&path)?;
-        store::ensure_sessions_parent(&path)?;
+    pub fn open(store: Arc<dyn SessionStore>, session_name: &str) -> Result<Self> {
+        let mut history = store.load(session_name)?;
+        let closed = repair::close_dangling_calls(&mut history);
+        let mut warnings = Vec::new();
+        if closed > 0 {
+            // The outputs go right after their calls, so the file is
+            // rewritten rather than appended to.
+            store.replace(session_name, &history)?;
+            warnings.push(format!(
+                "closed {closed} unfinished tool call(s) in {session_name}"
+            ));
+        }
 
         Ok(Self {
             session_name: session_name.to_string(),
-            path,
+            store: Some(store),
             history,
+            warnings,
         })
     }
 
+    /// A session that is never persisted.
+    pub fn in_memory(session_name: &str) -> Self {
+        Self {
+            session_name: session_name.to_string(),
+            store: None,
+            history: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
     pub fn session_name(&self) -> &str {
         &self.session_name
     }
 
+    /// Warnings not yet reported, oldest first.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn history(&self) -> &[Value] {
+        &self.history
+    }
+
     pub const fn history_mut(&mut self) -> &mut Vec<Value> {
         &mut self.history
     }
@@ -36,51 +66,32 @@ impl SessionManager {
         self.history.len()
     }
 
-    fn append_line(&self, line: &str) -> Result<()> {
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.path)
-            .with_context(|| {
-                format!(
-                    "failed to open session file for append: {}",
-                    self.path.display()
-                )
-            })?;
-        file.write_all(line.as_bytes()).with_context(|| {
-            format!("failed to append session entry to {}", self.path.display())
-        })?;
-        file.write_all(b"\n")
-            .with_context(|| format!("failed to append newline to {}", self.path.display()))?;
-        Ok(())
-    }
-
     pub fn append(&mut self, entry: Value) -> Result<()> {
-        let line = serde_json::to_string(&entry).with_context(|| {
-            format!(
-                "failed to serialize session entry for {}",
-                self.path.display()
-            )
-        })?;
+        if let Some(store) = &self.store {
+            store.append(&self.session_name, std::slice::from_ref(&entry))?;
+        }
         self.history.push(entry);
-        self.append_line(&line)?;
         Ok(())
     }
 
-    pub fn persist_from(&self, start: usize) -> Result<()> {
+    /// Close tool calls left without an output by an interrupted or failed
+    /// turn. Only entries from `start` onward are touched, so anything already
+    /// on disk keeps its position.
+    pub fn close_dangling_calls(&mut self, start: usize) {
         if start >= self.history.len() {
-            return Ok(());
+            return;
         }
+        let mut turn = self.history.split_off(start);
+        repair::close_dangling_calls(&mut turn);
+        self.history.append(&mut turn);
+    }
 
-        for entry in &self.history[start..] {
-            let line = serde_json::to_string(entry).with_context(|| {
-                format!(
-                    "failed to serialize session entry while persisting {}",
-                    self.path.display()
-                )
-            })?;
-            self.append_line(&line)?;
+    pub fn persist_from(&self, start: usize) -> Result<()> {
+        match &self.store {
+            Some(store) if start < self.history.len() => {
+                store.append(&self.session_name, &self.history[start..])
+            }
+            _ => Ok(()),
         }
-        Ok(())
     }
 }
@@ -0,0 +1,33 @@
+use super::{JsonlSessionStore, SessionManager, SessionStore};
+use serde_json::json;
+use std::sync::Arc;
+
+#[test]
+fn open_persists_closed_dangling_calls() {
+    let dir = std::env::temp_dir().join(format!("ox-sessions-{:016x}", fastrand::u64(..)));
+    let store = Arc::new(JsonlSessionStore::new(&dir).unwrap());
+    store
+        .append(
+            "s",
+            &[
+                json!({ "role": "user", "content": "list files" }),
+                json!({ "type": "function_call", "call_id": "a", "name": "ls", "arguments": "{}" }),
+                json!({ "role": "user", "content": "never mind" }),
+            ],
+        )
+        .unwrap();
+
+    let mut manager = SessionManager::open(store.clone(), "s").unwrap();
+    assert_eq!(manager.take_warnings().len(), 1);
+
+    let saved = store.load("s").unwrap();
+    assert_eq!(saved, manager.history());
+    assert_eq!(saved[2]["type"], "function_call_output");
+    assert_eq!(saved[2]["call_id"], "a");
+
+    let mut reopened = SessionManager::open(store, "s").unwrap();
+    assert!(reopened.take_warnings().is_empty());
+    assert_eq!(reopened.history().len(), 4);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,25 +1,23 @@
 mod manager;
+#[cfg(test)]
+mod manager_tests;
 mod naming;
+mod repair;
+#[cfg(test)]
+mod repair_tests;
 mod store;
 
-use anyhow::{Context, Result};
+use serde_json::Value;
 
 pub use manager::SessionManager;
 pub use naming::create_session_name;
+pub use store::{JsonlSessionStore, SessionStore};
 
-pub fn list_sessions() -> Result<()> {
-    let sessions = store::list_sessions().context("unable to list sessions")?;
-    if sessions.is_empty() {
-        println!("No sessions found.");
-    } else {
-        for name in &sessions {
-            println!("{name}");
-        }
-    }
-    Ok(())
-}
+/// Entry type for diagnostics recorded in the session, such as repaired
+/// tool-call arguments. They stay in the history but are never sent to the
+/// model.
+pub const DIAGNOSTIC_TYPE: &str = "ox_diagnostic";
 
-pub fn open_session(session_name: &str) -> Result<SessionManager> {
-    eprintln!("Using session: {session_name}");
-    SessionManager::open(session_name)
+pub fn is_diagnostic(entry: &Value) -> bool {
+    entry["type"] == DIAGNOSTIC_TYPE
 }
@@ -0,0 +1,40 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+const INTERRUPTED_OUTPUT: &str = "Error: interrupted by user before this tool call completed";
+
+/// Insert a synthetic output right after every `function_call` that has no
+/// matching `function_call_output`, so the history replays cleanly.
+/// Returns how many calls were closed.
+pub(super) fn close_dangling_calls(history: &mut Vec<Value>) -> usize {
+    let answered: HashSet<String> = history
+        .iter()
+        .filter(|entry| entry["type"] == "function_call_output")
+        .filter_map(|entry| entry["call_id"].as_str().map(str::to_string))
+        .collect();
+
+    let mut closed = 0;
+    let mut index = 0;
+    while index < history.len() {
+        let entry = &history[index];
+        index += 1;
+        if entry["type"] != "function_call" {
+            continue;
+        }
+        let call_id = entry["call_id"].as_str().unwrap_or("").to_string();
+        if answered.contains(&call_id) {
+            continue;
+        }
+        history.insert(
+            index,
+            serde_json::json!({
+                "type": "function_call_output",
+                "call_id": call_id,
+                "output": INTERRUPTED_OUTPUT
+            }),
+        );
+        index += 1;
+        closed += 1;
+    }
+    closed
+}
@@ -0,0 +1,56 @@
+use super::repair::close_dangling_calls;
+use serde_json::{Value, json};
+
+fn call(call_id: &str) -> Value {
+    json!({ "type": "function_call", "call_id": call_id, "name": "ls", "arguments": "{}" })
+}
+
+fn output(call_id: &str, text: &str) -> Value {
+    json!({ "type": "function_call_output", "call_id": call_id, "output": text })
+}
+
+#[test]
+fn close_dangling_calls_leaves_complete_history_alone() {
+    let mut history = vec![
+        json!({ "role": "user", "content": "hi" }),
+        call("a"),
+        output("a", "ok"),
+    ];
+    let expected = history.clone();
+
+    assert_eq!(close_dangling_calls(&mut history), 0);
+    assert_eq!(history, expected);
+}
+
+#[test]
+fn close_dangling_calls_inserts_output_after_each_dangling_call() {
+    let mut history = vec![call("a"), call("b"), output("a", "ok"), call("c")];
+
+    assert_eq!(close_dangling_calls(&mut history), 2);
+
+    let ids: Vec<(&str, &str)> = history
+        .iter()
+        .map(|entry| {
+            (
+                entry["type"].as_str().unwrap_or(""),
+                entry["call_id"].as_str().unwrap_or(""),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            ("function_call", "a"),
+            ("function_call", "b"),
+            ("function_call_output", "b"),
+            ("function_call_output", "a"),
+            ("function_call", "c"),
+            ("function_call_output", "c"),
+        ]
+    );
+    assert!(
+        history[2]["output"]
+            .as_str()
+            .is_some_and(|text| text.contains("interrupted by user"))
+    );
+}
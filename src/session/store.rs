@@ -1,52 +1,135 @@
 use anyhow::{Context, Result};
 use serde_json::Value;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use super::naming::validate_session_name;
 
-const SESSIONS_DIR: &str = ".sessions";
 const SESSION_EXT: &str = "jsonl";
 
-fn sessions_dir() -> Result<PathBuf> {
-    let cwd = std::env::current_dir().context("failed to determine current directory")?;
-    Ok(cwd.join(SESSIONS_DIR))
+/// Where session history lives. Entries are history items in Responses
+/// API form and are appended, except when a repair rewrites the session.
+pub trait SessionStore: Send + Sync {
+    /// Every entry of the session, or none if it does not exist yet.
+    fn load(&self, session_name: &str) -> Result<Vec<Value>>;
+
+    /// Append entries to the session, creating it if needed.
+    fn append(&self, session_name: &str, entries: &[Value]) -> Result<()>;
+
+    /// Replace every entry of the session.
+    fn replace(&self, session_name: &str, entries: &[Value]) -> Result<()>;
+
+    /// Session names, newest first.
+    fn list(&self) -> Result<Vec<String>>;
 }
 
-pub(super) fn session_path(session_name: &str) -> Result<PathBuf> {
-    validate_session_name(session_name)?;
-    let dir = sessions_dir()?;
-    Ok(dir.join(format!("{session_name}.{SESSION_EXT}")))
+/// One JSON Lines file per session in a directory.
+pub struct JsonlSessionStore {
+    dir: PathBuf,
 }
 
-pub fn list_sessions() -> Result<Vec<String>> {
-    let dir: PathBuf = sessions_dir()?;
-    if !dir.exists() {
-        return Ok(Vec::new());
+impl JsonlSessionStore {
+    /// A relative `dir` is resolved against the working directory.
+    pub fn new(dir: &Path) -> Result<Self> {
+        let cwd = std::env::current_dir().context("failed to determine current directory")?;
+        Ok(Self { dir: cwd.join(dir) })
     }
 
-    let mut sessions = Vec::new();
-    for entry in std::fs::read_dir(&dir)
-        .with_context(|| format!("failed to read sessions directory: {}", dir.display()))?
-    {
-        let Ok(entry) = entry else {
-            continue;
-        };
-        let path = entry.path();
-        if path.extension().and_then(|ext| ext.to_str()) != Some(SESSION_EXT) {
-            continue;
+    fn session_path(&self, session_name: &str) -> Result<PathBuf> {
+        validate_session_name(session_name)?;
+        Ok(self.dir.join(format!("{session_name}.{SESSION_EXT}")))
+    }
+
+    fn create_dir(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "failed to create sessions directory: {}",
+                self.dir.display()
+            )
+        })
+    }
+}
+
+impl SessionStore for JsonlSessionStore {
+    fn load(&self, session_name: &str) -> Result<Vec<Value>> {
+        load_history_file(&self.session_path(session_name)?)
+    }
+
+    fn append(&self, session_name: &str, entries: &[Value]) -> Result<()> {
+        let path = self.session_path(session_name)?;
+        let lines = to_lines(&path, entries)?;
+        self.create_dir()?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| {
+                format!("failed to open session file for append: {}", path.display())
+            })?;
+        file.write_all(lines.as_bytes())
+            .with_context(|| format!("failed to append session entries to {}", path.display()))
+    }
+
+    fn replace(&self, session_name: &str, entries: &[Value]) -> Result<()> {
+        let path = self.session_path(session_name)?;
+        let lines = to_lines(&path, entries)?;
+        self.create_dir()?;
+        // Write through a temp file and rename, so a crash never leaves a
+        // half-written session behind.
+        let tmp_path = path.with_extension(format!("{SESSION_EXT}.tmp-{}", std::process::id()));
+        if let Err(e) = std::fs::write(&tmp_path, lines) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e).with_context(|| format!("failed to write {}", tmp_path.display()));
         }
-        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
-            continue;
-        };
-        sessions.push(stem.to_string());
+        if let Err(e) = std::fs::rename(&tmp_path, &path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e).with_context(|| format!("failed to replace {}", path.display()));
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let dir = &self.dir;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in std::fs::read_dir(dir)
+            .with_context(|| format!("failed to read sessions directory: {}", dir.display()))?
+        {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(SESSION_EXT) {
+                continue;
+            }
+            let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            sessions.push(stem.to_string());
+        }
+
+        sessions.sort();
+        sessions.reverse();
+        Ok(sessions)
     }
+}
 
-    sessions.sort();
-    sessions.reverse();
-    Ok(sessions)
+/// One JSON line per entry.
+fn to_lines(path: &Path, entries: &[Value]) -> Result<String> {
+    let mut lines = String::new();
+    for entry in entries {
+        let line = serde_json::to_string(entry)
+            .with_context(|| format!("failed to serialize session entry for {}", path.display()))?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    Ok(lines)
 }
 
-pub(super) fn load_history_file(path: &Path) -> Result<Vec<Value>> {
+fn load_history_file(path: &Path) -> Result<Vec<Value>> {
     if !path.exists() {
         return Ok(Vec::new());
     }
@@ -72,12 +155,3 @@ pub(super) fn load_history_file(path: &Path) -> Result<Vec<Value>> {
 
     Ok(history)
 }
-
-pub(super) fn ensure_sessions_parent(path: &Path) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).with_context(|| {
-            format!("failed to create sessions directory: {}", parent.display())
-        })?;
-    }
-    Ok(())
-}
@@ -0,0 +1,121 @@
+use ox::{AgentEvent, Approval, ApprovalRequest, Approver, CancellationToken, EventSink};
+use std::io::{self, BufRead, Write};
+
+/// Renders a turn in the REPL: streamed text on stdout, tool progress and
+/// errors as they happen.
+#[derive(Default)]
+pub struct TerminalSink {
+    /// Text has been printed since the last complete message.
+    mid_message: bool,
+    /// A retry countdown is on the current stderr line.
+    counting_down: bool,
+}
+
+impl EventSink for TerminalSink {
+    fn on_event(&mut self, event: &AgentEvent) {
+        if std::mem::take(&mut self.counting_down) {
+            eprintln!();
+        }
+        match event {
+            AgentEvent::TextDelta(delta) => {
+                self.mid_message = true;
+                print!("{delta}");
+                let _ = io::stdout().flush();
+            }
+            AgentEvent::MessageFinished { .. } => {
+                self.mid_message = false;
+                println!();
+            }
+            AgentEvent::ToolCallStarted { name, .. } => println!("Calling {name}..."),
+            AgentEvent::Error(error) => {
+                if std::mem::take(&mut self.mid_message) {
+                    println!();
+                }
+                eprintln!("Error: {error}");
+            }
+            AgentEvent::Warning(warning) => eprintln!("Warning: {warning}"),
+            AgentEvent::Retrying {
+                reason,
+                attempt,
+                max_attempts,
+                remaining,
+            } => {
+                if std::mem::take(&mut self.mid_message) {
+                    println!();
+                }
+                eprint!(
+                    "\r{reason}. Retrying in {}s (attempt {attempt}/{max_attempts})...   ",
+                    remaining.as_secs_f64().ceil()
+                );
+                let _ = io::stderr().flush();
+                self.counting_down = true;
+            }
+            AgentEvent::TurnFinished {
+                hit_tool_limit: true,
+            } => eprintln!("Warning: stopped at the tool-call limit (tools.max_tool_calls)."),
+            AgentEvent::TurnStarted
+            | AgentEvent::ToolCallFinished { .. }
+            | AgentEvent::Usage(_)
+            | AgentEvent::TurnFinished { .. } => {}
+        }
+    }
+}
+
+/// Asks on the terminal before a tool call runs.
+pub struct TerminalApprover;
+
+impl Approver for TerminalApprover {
+    fn approve(&self, request: &ApprovalRequest, cancel: &CancellationToken) -> Approval {
+        eprintln!("\n{} wants to run:", request.name);
+        for line in request.preview.lines() {
+            eprintln!("  {line}");
+        }
+        eprint!(
+            "Allow? [y]es, [a]lways allow {} this session, [n]o, or type a reason to deny: ",
+            request.always
+        );
+        let _ = io::stderr().flush();
+        read_answer(cancel).map_or(Approval::Deny(None), |answer| parse_answer(&answer))
+    }
+}
+
+fn parse_answer(answer: &str) -> Approval {
+    match answer.trim() {
+        "y" | "yes" => Approval::Approve,
+        "a" | "always" => Approval::AlwaysAllow,
+        "" | "n" | "no" => Approval::Deny(None),
+        reason => Approval::Deny(Some(reason.to_string())),
+    }
+}
+
+/// Read one line from stdin, giving up when `cancel` fires (Ctrl+C) so the
+/// line isn't swallowed after the turn has ended. `None` on cancel, EOF or
+/// a failed poll.
+fn read_answer(cancel: &CancellationToken) -> Option<String> {
+    let mut stdin_fd = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    loop {
+        if cancel.is_cancelled() {
+            eprintln!();
+            return None;
+        }
+        // SAFETY: `stdin_fd` is a single valid `pollfd` that outlives the
+        // call.
+        let ready = unsafe { libc::poll(&raw mut stdin_fd, 1, 100) };
+        if ready > 0 {
+            break;
+        }
+        // A signal such as Ctrl+C interrupts the poll; check `cancel` and
+        // poll again. Any other failure denies rather than block on stdin.
+        if ready < 0 && io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            eprintln!();
+            return None;
+        }
+    }
+    let mut line = String::new();
+    let read = io::stdin().lock().read_line(&mut line);
+    matches!(read, Ok(n) if n > 0).then_some(line)
+}
@@ -0,0 +1,65 @@
+//! A stub HTTP server for tests that talk to a model or auth API. The
+//! binary's tests include this file on its own, so it only uses std and
+//! tokio.
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// One request a stub server received.
+pub struct Request {
+    /// The request line and headers.
+    pub head: String,
+    pub body: String,
+}
+
+/// Serve on a local port until the test ends, one request per connection,
+/// answering each with the status and body `respond` returns. Returns the
+/// base URL.
+pub async fn spawn_server(
+    content_type: &'static str,
+    mut respond: impl FnMut(Request) -> (u16, String) + Send + 'static,
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.expect("accept");
+            let request = read_request(&mut stream).await;
+            let (status, body) = respond(request);
+            let response = format!(
+                "HTTP/1.1 {status} Stub\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.expect("write");
+        }
+    });
+    format!("http://{addr}")
+}
+
+/// Read one request, its body up to `content-length`.
+async fn read_request(stream: &mut TcpStream) -> Request {
+    let mut buf = Vec::new();
+    let mut chunk = [0_u8; 4096];
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await.expect("read request");
+        buf.extend_from_slice(&chunk[..read]);
+        if let Some(pos) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..header_end - 4]).to_string();
+    let content_length = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())?
+        })
+        .unwrap_or(0);
+    while buf.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await.expect("read body");
+        buf.extend_from_slice(&chunk[..read]);
+    }
+    let body = String::from_utf8_lossy(&buf[header_end..]).to_string();
+    Request { head, body }
+}
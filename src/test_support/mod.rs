@@ -0,0 +1,17 @@
+//! Helpers shared by the unit tests.
+
+mod http;
+
+pub use http::spawn_server;
+
+use crate::agent::{AgentEvent, EventSink};
+
+/// Keeps every event it is sent.
+#[derive(Default)]
+pub struct Recorder(pub Vec<AgentEvent>);
+
+impl EventSink for Recorder {
+    fn on_event(&mut self, event: &AgentEvent) {
+        self.0.push(event.clone());
+    }
+}
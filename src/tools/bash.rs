@@ -1,152 +1,99 @@
-use super::truncate;
+use super::process::{self, ProcessOutcome};
+use super::{OutputLimits, Tool, ToolContext};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use std::fmt::Write;
-use std::io::Read;
-use std::process::{Command, ExitStatus, Stdio};
-use std::thread;
-use std::thread::JoinHandle;
-use std::time::{Duration, Instant};
-
-pub fn definition() -> serde_json::Value {
-    serde_json::json!({
-        "type": "function",
-        "name": "bash",
-        "description": "Execute a shell command and return its output. Output is truncated to the last 2000 lines or 50KB. Optionally provide timeout in seconds.",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "command": { "type": "string", "description": "The shell command to execute" },
-                "timeout": { "type": "integer", "description": "Timeout in seconds (optional, no default timeout)" }
-            },
-            "required": ["command"]
-        }
-    })
+use std::num::NonZeroU64;
+use std::process::Command;
+use std::time::Duration;
+
+pub(super) struct Bash;
+
+#[derive(Deserialize, JsonSchema)]
+pub(super) struct Args {
+    /// The shell command to execute
+    command: String,
+    /// Timeout in seconds (optional, no default timeout)
+    timeout: Option<NonZeroU64>,
 }
 
-pub fn run(args: &serde_json::Value) -> String {
-    let Some(command) = args["command"].as_str() else {
-        return "Error: missing 'command' argument".to_string();
-    };
-
-    let timeout = match parse_timeout(args) {
-        Ok(timeout) => timeout,
-        Err(err) => return err,
-    };
-
-    let outcome = match execute_command(command, timeout) {
-        Ok(outcome) => outcome,
-        Err(err) => return err,
-    };
-
-    let exit_code = outcome.status.code().unwrap_or(-1);
-    let result = combine_streams(&outcome.stdout, &outcome.stderr);
+impl Tool for Bash {
+    type Args = Args;
 
-    if outcome.timed_out {
-        return format_timeout_error(timeout, &result);
+    fn name(&self) -> &'static str {
+        "bash"
     }
 
-    if !outcome.status.success() {
-        if result.is_empty() {
-            return format!("Error: command exited with code {exit_code}");
-        }
-        return format!(
-            "Error: command exited with code {exit_code}\n{}",
-            truncate::tail(&result)
-        );
+    fn description(&self) -> &'static str {
+        "Execute a shell command and return its output. Long output is truncated to its last lines. Optionally provide timeout in seconds. Processes started in the background are killed when the command exits."
     }
 
-    if result.is_empty() {
-        format!("Command exited with code {exit_code}")
-    } else {
-        truncate::tail(&result)
+    fn is_mutating(&self) -> bool {
+        true
     }
-}
 
-fn parse_timeout(args: &serde_json::Value) -> Result<Option<Duration>, String> {
-    match args.get("timeout") {
-        Some(value) if !value.is_null() => match value.as_u64() {
-            Some(seconds) if seconds > 0 => Ok(Some(Duration::from_secs(seconds))),
-            _ => Err("Error: 'timeout' must be a positive integer".to_string()),
-        },
-        _ => Ok(None),
+    fn preview(&self, args: &Args) -> Option<String> {
+        Some(format!("$ {}", args.command))
     }
-}
 
-struct CommandOutcome {
-    status: ExitStatus,
-    stdout: String,
-    stderr: String,
-    timed_out: bool,
-}
+    fn run(&self, args: Args, ctx: &ToolContext) -> Result<String, String> {
+        let timeout = args
+            .timeout
+            .map(|seconds| Duration::from_secs(seconds.get()));
 
-fn execute_command(command: &str, timeout: Option<Duration>) -> Result<CommandOutcome, String> {
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Error: {e}"))?;
-
-    let stdout_thread = child.stdout.take().map(spawn_reader);
-    let stderr_thread = child.stderr.take().map(spawn_reader);
-
-    let mut timed_out = false;
-    let status = wait_for_exit(&mut child, timeout, &mut timed_out)?;
-    let stdout_bytes = join_reader(stdout_thread);
-    let stderr_bytes = join_reader(stderr_thread);
-
-    Ok(CommandOutcome {
-        status,
-        stdout: String::from_utf8_lossy(&stdout_bytes).into_owned(),
-        stderr: String::from_utf8_lossy(&stderr_bytes).into_owned(),
-        timed_out,
-    })
-}
+        let outcome = execute_command(&args.command, timeout, ctx)?;
 
-fn wait_for_exit(
-    child: &mut std::process::Child,
-    timeout: Option<Duration>,
-    timed_out: &mut bool,
-) -> Result<ExitStatus, String> {
-    let start = Instant::now();
-
-    loop {
-        match child.try_wait() {
-            Ok(Some(status)) => return Ok(status),
-            Ok(None) => {
-                if let Some(limit) = timeout
-                    && start.elapsed() >= limit
-                {
-                    *timed_out = true;
-                    let _ = child.kill();
-                    return child
-                        .wait()
-                        .map_err(|e| format!("Error: failed waiting for process: {e}"));
-                }
-                thread::sleep(Duration::from_millis(25));
+        let exit_code = outcome.status.code().unwrap_or(-1);
+        let result = combine_streams(
+            &String::from_utf8_lossy(&outcome.stdout),
+            &String::from_utf8_lossy(&outcome.stderr),
+        );
+
+        if outcome.cancelled {
+            return Err(format_cancelled_error(&result, ctx.limits));
+        }
+
+        if outcome.timed_out {
+            return Err(format_timeout_error(timeout, &result, ctx.limits));
+        }
+
+        if !outcome.status.success() {
+            let mut error = format!("Error: command exited with code {exit_code}");
+            if !result.is_empty() {
+                let _ = write!(error, "\n{}", ctx.limits.tail(&result));
             }
-            Err(e) => return Err(format!("Error: failed to poll process status: {e}")),
+            if let Some(note) = ctx.sandbox.explain_failure(&result) {
+                error = format!("{}\n{note}", error.trim_end());
+            }
+            return Err(error);
+        }
+
+        if result.is_empty() {
+            Ok(format!("Command exited with code {exit_code}"))
+        } else {
+            Ok(ctx.limits.tail(&result))
         }
     }
 }
 
-fn spawn_reader<R>(mut pipe: R) -> JoinHandle<Vec<u8>>
-where
-    R: Read + Send + 'static,
-{
-    thread::spawn(move || {
-        let mut buf = Vec::new();
-        let _ = pipe.read_to_end(&mut buf);
-        buf
+fn execute_command(
+    command: &str,
+    timeout: Option<Duration>,
+    ctx: &ToolContext,
+) -> Result<ProcessOutcome, String> {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    ctx.env.apply(&mut shell);
+    ctx.sandbox.apply(&mut shell)?;
+    process::run(&mut shell, timeout, &ctx.cancel).map_err(|e| {
+        if ctx.sandbox.is_enabled() {
+            ctx.sandbox.setup_error(&e)
+        } else {
+            format!("Error: {e}")
+        }
     })
 }
 
-fn join_reader(handle: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
-    handle
-        .and_then(|thread| thread.join().ok())
-        .unwrap_or_default()
-}
-
 fn combine_streams(stdout: &str, stderr: &str) -> String {
     let mut result = String::new();
     if !stdout.is_empty() {
@@ -161,7 +108,7 @@ fn combine_streams(stdout: &str, stderr: &str) -> String {
     result
 }
 
-fn format_timeout_error(timeout: Option<Duration>, result: &str) -> String {
+fn format_timeout_error(timeout: Option<Duration>, result: &str, limits: OutputLimits) -> String {
     if result.is_empty() {
         return timeout.map_or_else(
             || "Error: command timed out".to_string(),
@@ -170,13 +117,21 @@ fn format_timeout_error(timeout: Option<Duration>, result: &str) -> String {
     }
 
     timeout.map_or_else(
-        || format!("Error: command timed out\n{}", truncate::tail(result)),
+        || format!("Error: command timed out\n{}", limits.tail(result)),
         |limit| {
             format!(
                 "Error: command timed out after {} seconds\n{}",
                 limit.as_secs(),
-                truncate::tail(result)
+                limits.tail(result)
             )
         },
     )
 }
+
+fn format_cancelled_error(result: &str, limits: OutputLimits) -> String {
+    if result.is_empty() {
+        "Error: command cancelled by user".to_string()
+    } else {
+        format!("Error: command cancelled by user\n{}", limits.tail(result))
+    }
+}
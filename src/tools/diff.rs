@@ -0,0 +1,33 @@
+use std::fmt::Write;
+
+/// Changed lines shown per side before the preview is cut short.
+const MAX_LINES: usize = 40;
+
+/// A short diff for approval prompts: the lines between the common prefix
+/// and suffix of `old` and `new`, removed then added.
+pub(super) fn preview(path: &str, old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut out = format!("--- {path}\n+++ {path}\n@@ line {} @@\n", prefix + 1);
+    append(&mut out, '-', &old[prefix..old.len() - suffix]);
+    append(&mut out, '+', &new[prefix..new.len() - suffix]);
+    out.truncate(out.trim_end().len());
+    out
+}
+
+fn append(out: &mut String, sign: char, lines: &[&str]) {
+    for line in lines.iter().take(MAX_LINES) {
+        let _ = writeln!(out, "{sign}{line}");
+    }
+    if lines.len() > MAX_LINES {
+        let _ = writeln!(out, "{sign}... {} more lines", lines.len() - MAX_LINES);
+    }
+}
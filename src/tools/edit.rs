@@ -1,48 +1,73 @@
-pub fn definition() -> serde_json::Value {
-    serde_json::json!({
-        "type": "function",
-        "name": "edit",
-        "description": "Edit a file by replacing exact text. The old_text must match exactly including whitespace. Use read_file first to see the current content.",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "path": { "type": "string", "description": "The file path to edit" },
-                "old_text": { "type": "string", "description": "The exact text to find (must match exactly, must be unique in the file)" },
-                "new_text": { "type": "string", "description": "The text to replace it with" }
-            },
-            "required": ["path", "old_text", "new_text"]
-        }
-    })
+use super::{Tool, ToolContext, diff};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub(super) struct Edit;
+
+#[derive(Deserialize, JsonSchema)]
+pub(super) struct Args {
+    /// The file path to edit
+    path: String,
+    /// The exact text to find (must match exactly, must be unique in the file)
+    old_text: String,
+    /// The text to replace it with
+    new_text: String,
 }
 
-pub fn run(args: &serde_json::Value) -> String {
-    let Some(path) = args["path"].as_str() else {
-        return "Error: missing 'path' argument".to_string();
-    };
-    let Some(old_text) = args["old_text"].as_str() else {
-        return "Error: missing 'old_text' argument".to_string();
-    };
-    let Some(new_text) = args["new_text"].as_str() else {
-        return "Error: missing 'new_text' argument".to_string();
-    };
-
-    let content = match std::fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(e) => return format!("Error reading file: {e}"),
-    };
-
-    let count = content.matches(old_text).count();
-    match count {
-        0 => "Error: old_text not found in file".to_string(),
-        1 => {
-            let new_content = content.replacen(old_text, new_text, 1);
-            match std::fs::write(path, new_content) {
-                Ok(()) => format!("Successfully edited {path}"),
-                Err(e) => format!("Error writing file: {e}"),
+impl Tool for Edit {
+    type Args = Args;
+
+    fn name(&self) -> &'static str {
+        "edit"
+    }
+
+    fn description(&self) -> &'static str {
+        "Edit a file by replacing exact text. The old_text must match exactly including whitespace. Use read_file first to see the current content."
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    /// The edit in the context of the file when it applies, otherwise just
+    /// the replaced text.
+    fn preview(&self, args: &Args) -> Option<String> {
+        let preview = match std::fs::read_to_string(&args.path) {
+            Ok(content) if content.matches(&args.old_text).count() == 1 => {
+                let new_content = content.replacen(&args.old_text, &args.new_text, 1);
+                diff::preview(&args.path, &content, &new_content)
+            }
+            _ => diff::preview(&args.path, &args.old_text, &args.new_text),
+        };
+        Some(preview)
+    }
+
+    fn path<'a>(&self, args: &'a Args) -> Option<&'a str> {
+        Some(&args.path)
+    }
+
+    fn run(&self, args: Args, ctx: &ToolContext) -> Result<String, String> {
+        let Args {
+            path,
+            old_text,
+            new_text,
+        } = args;
+        let resolved = ctx.workspace.check(&path)?;
+        let content =
+            std::fs::read_to_string(&resolved).map_err(|e| format!("Error reading file: {e}"))?;
+
+        let count = content.matches(&old_text).count();
+        match count {
+            0 => Err("Error: old_text not found in file".to_string()),
+            1 => {
+                let new_content = content.replacen(&old_text, &new_text, 1);
+                std::fs::write(&resolved, new_content)
+                    .map(|()| format!("Successfully edited {path}"))
+                    .map_err(|e| format!("Error writing file: {e}"))
             }
+            n => Err(format!(
+                "Error: old_text found {n} times, include more surrounding context to make it unique"
+            )),
         }
-        n => format!(
-            "Error: old_text found {n} times, include more surrounding context to make it unique"
-        ),
     }
 }
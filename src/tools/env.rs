@@ -0,0 +1,71 @@
+use crate::auth::CREDENTIAL_VARS;
+use crate::config::{self, Config};
+use crate::permissions::wildcard_matches;
+use std::ffi::OsString;
+use std::process::Command;
+
+/// Variables that usually hold secrets; `tools.env.allow` lets one through.
+const DENIED_BY_DEFAULT: &[&str] = &[
+    "*_API_KEY",
+    "*_ACCESS_KEY*",
+    "*_SECRET*",
+    "*_TOKEN",
+    "*PASSWORD*",
+    "*_CREDENTIALS",
+];
+
+/// The environment processes started by ox begin from.
+///
+/// That is ox's own environment minus its credentials, whatever `.env`
+/// loaded, and variables matching a deny pattern that no allow pattern
+/// lets through. `bash`, the search tools and MCP stdio servers use it.
+#[derive(Clone, Debug)]
+pub struct CommandEnv {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl Default for CommandEnv {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: DENIED_BY_DEFAULT.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+impl CommandEnv {
+    /// The built-in deny patterns plus `tools.env.deny`, with
+    /// `tools.env.allow` as exceptions.
+    pub fn from_config(config: &Config) -> Self {
+        let mut env = Self::default();
+        let patterns = |key| config.list(key).map(|(pattern, _)| pattern.to_string());
+        env.allow.extend(patterns("tools.env.allow"));
+        env.deny.extend(patterns("tools.env.deny"));
+        env
+    }
+
+    /// Whether a variable reaches commands.
+    pub fn passes(&self, name: &str) -> bool {
+        if CREDENTIAL_VARS.contains(&name) || config::dotenv_names().iter().any(|n| n == name) {
+            return false;
+        }
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| wildcard_matches(pattern, name))
+        };
+        matches(&self.allow) || !matches(&self.deny)
+    }
+
+    /// The variables of ox's environment that pass.
+    pub fn vars(&self) -> impl Iterator<Item = (OsString, OsString)> + '_ {
+        // Names that aren't UTF-8 can't be matched and are dropped.
+        std::env::vars_os().filter(|(name, _)| name.to_str().is_some_and(|name| self.passes(name)))
+    }
+
+    /// Give `command` only the variables that pass.
+    pub(crate) fn apply(&self, command: &mut Command) {
+        command.env_clear().envs(self.vars());
+    }
+}
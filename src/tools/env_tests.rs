@@ -0,0 +1,54 @@
+use super::CommandEnv;
+use crate::config::Config;
+
+#[test]
+fn credentials_and_secret_looking_variables_are_dropped() {
+    let env = CommandEnv::default();
+
+    for name in ["PATH", "HOME", "CARGO_HOME", "TOKENIZERS_PARALLELISM"] {
+        assert!(env.passes(name), "{name}");
+    }
+    for name in [
+        "OPENAI_API_KEY",
+        "OX_API_KEY_COMMAND",
+        "GITHUB_TOKEN",
+        "AWS_SECRET_ACCESS_KEY",
+        "AWS_ACCESS_KEY_ID",
+        "PGPASSWORD",
+    ] {
+        assert!(!env.passes(name), "{name}");
+    }
+}
+
+#[test]
+fn configured_patterns_add_to_the_defaults() {
+    let config = Config::from_layers(
+        Vec::new(),
+        |_| None,
+        &[
+            (
+                "tools.env.allow".to_string(),
+                r#"["GITHUB_TOKEN"]"#.to_string(),
+            ),
+            (
+                "tools.env.deny".to_string(),
+                r#"["INTERNAL_*"]"#.to_string(),
+            ),
+        ],
+    )
+    .expect("valid config");
+    let env = CommandEnv::from_config(&config);
+
+    assert!(env.passes("GITHUB_TOKEN"));
+    assert!(!env.passes("GITLAB_TOKEN"));
+    assert!(!env.passes("INTERNAL_URL"));
+    assert!(env.passes("PATH"));
+
+    let config = Config::from_layers(
+        Vec::new(),
+        |_| None,
+        &[("tools.env.allow".to_string(), r#"["*"]"#.to_string())],
+    )
+    .expect("valid config");
+    assert!(!CommandEnv::from_config(&config).passes("ANTHROPIC_API_KEY"));
+}
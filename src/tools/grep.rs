@@ -1,45 +1,62 @@
-use super::truncate;
-
-pub fn definition() -> serde_json::Value {
-    serde_json::json!({
-        "type": "function",
-        "name": "grep",
-        "description": "Search for a text pattern in files within a directory. Returns matching lines with file paths and line numbers.",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "pattern": { "type": "string", "description": "The text pattern to search for" },
-                "path": { "type": "string", "description": "The directory to search in. Defaults to current directory." }
-            },
-            "required": ["pattern"]
-        }
-    })
+use super::{Tool, ToolContext, process};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::process::Command;
+
+pub(super) struct Grep;
+
+#[derive(Deserialize, JsonSchema)]
+pub(super) struct Args {
+    /// The text pattern to search for
+    pattern: String,
+    /// The directory to search in. Defaults to current directory.
+    path: Option<String>,
 }
 
-pub fn run(args: &serde_json::Value) -> String {
-    let Some(pattern) = args["pattern"].as_str() else {
-        return "Error: missing 'pattern' argument".to_string();
-    };
-    let path = args["path"].as_str().unwrap_or(".");
-
-    let result = std::process::Command::new("rg")
-        .args(["-n", "--no-heading", pattern, path])
-        .output()
-        .or_else(|_| {
-            std::process::Command::new("grep")
-                .args(["-rn", pattern, path])
-                .output()
-        });
-
-    match result {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            if stdout.is_empty() {
-                format!("No matches found for '{pattern}'")
-            } else {
-                truncate::head(&stdout, 100, "matches remaining")
+impl Tool for Grep {
+    type Args = Args;
+
+    fn name(&self) -> &'static str {
+        "grep"
+    }
+
+    fn description(&self) -> &'static str {
+        "Search for a text pattern in files within a directory. Returns matching lines with file paths and line numbers."
+    }
+
+    fn is_mutating(&self) -> bool {
+        false
+    }
+
+    fn path<'a>(&self, args: &'a Args) -> Option<&'a str> {
+        Some(args.path.as_deref().unwrap_or("."))
+    }
+
+    fn run(&self, args: Args, ctx: &ToolContext) -> Result<String, String> {
+        let pattern = args.pattern.as_str();
+        let path = args.path.as_deref().unwrap_or(".");
+        ctx.workspace.check(path)?;
+
+        let run = |program: &str, args: &[&str]| {
+            let mut command = Command::new(program);
+            command.args(args);
+            ctx.env.apply(&mut command);
+            process::run(&mut command, None, &ctx.cancel)
+        };
+        let result = run("rg", &["-n", "--no-heading", pattern, path])
+            .or_else(|_| run("grep", &["-rn", pattern, path]));
+
+        match result {
+            Ok(output) if output.cancelled => Err("Error: search cancelled by user".to_string()),
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                if stdout.is_empty() {
+                    Ok(format!("No matches found for '{pattern}'"))
+                } else {
+                    Ok(ctx.limits.head(&stdout, "matches remaining"))
+                }
             }
+            Err(e) => Err(format!("Error: {e}")),
         }
-        Err(e) => format!("Error: {e}"),
     }
 }
@@ -1,32 +1,49 @@
-use super::truncate;
-
-pub fn definition() -> serde_json::Value {
-    serde_json::json!({
-        "type": "function",
-        "name": "ls",
-        "description": "List files and directories at the given path",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "path": { "type": "string", "description": "The directory path to list. Defaults to current directory if not provided." }
-            }
-        }
-    })
+use super::{Tool, ToolContext};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub(super) struct Ls;
+
+#[derive(Deserialize, JsonSchema)]
+pub(super) struct Args {
+    /// The directory path to list. Defaults to current directory if not provided.
+    path: Option<String>,
 }
 
-pub fn run(args: &serde_json::Value) -> String {
-    let path = args["path"].as_str().unwrap_or(".");
-    match std::fs::read_dir(path) {
-        Ok(entries) => {
-            let mut items: Vec<String> = Vec::new();
-            for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_string();
-                let suffix = if entry.path().is_dir() { "/" } else { "" };
-                items.push(format!("{name}{suffix}"));
+impl Tool for Ls {
+    type Args = Args;
+
+    fn name(&self) -> &'static str {
+        "ls"
+    }
+
+    fn description(&self) -> &'static str {
+        "List files and directories at the given path"
+    }
+
+    fn is_mutating(&self) -> bool {
+        false
+    }
+
+    fn path<'a>(&self, args: &'a Args) -> Option<&'a str> {
+        Some(args.path.as_deref().unwrap_or("."))
+    }
+
+    fn run(&self, args: Args, ctx: &ToolContext) -> Result<String, String> {
+        let path = args.path.as_deref().unwrap_or(".");
+        ctx.workspace.check(path)?;
+        match std::fs::read_dir(path) {
+            Ok(entries) => {
+                let mut items: Vec<String> = Vec::new();
+                for entry in entries.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let suffix = if entry.path().is_dir() { "/" } else { "" };
+                    items.push(format!("{name}{suffix}"));
+                }
+                items.sort();
+                Ok(ctx.limits.head(&items.join("\n"), "entries remaining"))
             }
-            items.sort();
-            truncate::head(&items.join("\n"), 500, "entries remaining")
+            Err(e) => Err(format!("Error: {e}")),
         }
-        Err(e) => format!("Error: {e}"),
     }
 }
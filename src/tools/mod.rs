@@ -1,38 +1,202 @@
 mod bash;
+mod diff;
 mod edit;
+mod env;
+#[cfg(test)]
+mod env_tests;
 mod find;
 mod grep;
 mod ls;
+mod process;
+#[cfg(test)]
+mod process_tests;
 mod read_file;
+mod registry;
+#[cfg(test)]
+mod registry_tests;
+mod repair;
+#[cfg(test)]
+mod repair_tests;
+mod schema;
 mod truncate;
+mod workspace;
+#[cfg(test)]
+mod workspace_tests;
 mod write_file;
 
-pub fn definitions() -> Vec<serde_json::Value> {
-    vec![
-        read_file::definition(),
-        ls::definition(),
-        write_file::definition(),
-        edit::definition(),
-        grep::definition(),
-        find::definition(),
-        bash::definition(),
-    ]
+use crate::sandbox::Sandbox;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+pub use env::CommandEnv;
+pub use registry::ToolRegistry;
+pub(crate) use repair::repair_arguments;
+pub use truncate::OutputLimits;
+pub use workspace::{OutsideAccess, Workspace, real_path};
+
+/// Per-call state shared by every tool.
+#[derive(Clone, Debug, Default)]
+pub struct ToolContext {
+    pub cancel: CancellationToken,
+    pub limits: OutputLimits,
+    /// Where `bash` commands run; off by default.
+    pub sandbox: Sandbox,
+    /// The variables `bash` commands see.
+    pub env: CommandEnv,
+    /// Where the file tools may read and write; unconfined by default.
+    pub workspace: Workspace,
 }
 
-pub fn execute(name: &str, arguments: &str) -> String {
-    let args: serde_json::Value = match serde_json::from_str(arguments) {
-        Ok(v) => v,
-        Err(e) => return format!("Error parsing arguments: {e}"),
+/// A function the model can call. Tools run on a blocking thread and
+/// report failures as an `Error: ...` string the model can read.
+pub trait Tool: Send + Sync {
+    /// The arguments the model passes. The JSON Schema offered to the model
+    /// is generated from this type, and field doc comments become the
+    /// parameter descriptions.
+    type Args: DeserializeOwned + JsonSchema;
+
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Whether the tool can change files or run arbitrary commands.
+    /// Mutating calls never run in parallel with other calls.
+    fn is_mutating(&self) -> bool;
+
+    /// What the call will do, shown when asking the user to approve it:
+    /// a command, a diff. `None` shows the arguments instead.
+    fn preview(&self, _args: &Self::Args) -> Option<String> {
+        None
+    }
+
+    /// The file or directory the call works on, checked against the
+    /// workspace before the call is approved.
+    fn path<'a>(&self, _args: &'a Self::Args) -> Option<&'a str> {
+        None
+    }
+
+    /// The output for the model, or `Err` with an "Error: ..." message
+    /// when the call failed.
+    fn run(&self, args: Self::Args, ctx: &ToolContext) -> Result<String, String>;
+}
+
+/// The object-safe side of [`Tool`] that the registry stores: arguments
+/// arrive as the raw JSON string the model produced.
+pub(crate) trait DynTool: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// The function definition in Responses API form (`type`, `name`,
+    /// `description`, `parameters`, `strict`).
+    fn definition(&self, strict: bool) -> Value;
+
+    fn is_mutating(&self) -> bool;
+
+    fn preview(&self, arguments: &str) -> String {
+        pretty_arguments(arguments)
+    }
+
+    fn path(&self, _arguments: &str) -> Option<String> {
+        None
+    }
+
+    fn call(&self, arguments: &str, ctx: &ToolContext) -> Result<String, String>;
+}
+
+impl<T: Tool> DynTool for T {
+    fn name(&self) -> &str {
+        Tool::name(self)
+    }
+
+    fn description(&self) -> &str {
+        Tool::description(self)
+    }
+
+    fn definition(&self, strict: bool) -> Value {
+        serde_json::json!({
+            "type": "function",
+            "name": Tool::name(self),
+            "description": Tool::description(self),
+            "parameters": schema::parameters::<T::Args>(strict),
+            "strict": strict
+        })
+    }
+
+    fn is_mutating(&self) -> bool {
+        Tool::is_mutating(self)
+    }
+
+    fn preview(&self, arguments: &str) -> String {
+        serde_json::from_str(arguments)
+            .ok()
+            .and_then(|args| Tool::preview(self, &args))
+            .unwrap_or_else(|| pretty_arguments(arguments))
+    }
+
+    fn path(&self, arguments: &str) -> Option<String> {
+        let args = serde_json::from_str(arguments).ok()?;
+        Tool::path(self, &args).map(str::to_string)
+    }
+
+    fn call(&self, arguments: &str, ctx: &ToolContext) -> Result<String, String> {
+        let deserializer = &mut serde_json::Deserializer::from_str(arguments);
+        match serde_path_to_error::deserialize(deserializer) {
+            Ok(args) => self.run(args, ctx),
+            Err(e) => {
+                let schema = schema::parameters::<T::Args>(false);
+                Err(invalid_arguments(Tool::name(self), &e, &schema))
+            }
+        }
+    }
+}
+
+fn pretty_arguments(arguments: &str) -> String {
+    serde_json::from_str::<Value>(arguments)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| arguments.to_string())
+}
+
+/// One message shape for every argument error: the parse position for
+/// broken JSON or the offending field otherwise, then the expected schema
+/// so the model can retry.
+fn invalid_arguments(
+    name: &str,
+    error: &serde_path_to_error::Error<serde_json::Error>,
+    schema: &Value,
+) -> String {
+    let inner = error.inner();
+    let path = error.path().to_string();
+    let problem = if inner.is_syntax() || inner.is_eof() {
+        let position = format!(" at line {} column {}", inner.line(), inner.column());
+        let message = inner.to_string();
+        format!(
+            "invalid JSON in arguments for {name}{position}: {}",
+            message.strip_suffix(&position).unwrap_or(&message)
+        )
+    } else if path == "." {
+        format!("invalid arguments for {name}: {inner}")
+    } else {
+        format!("invalid arguments for {name}: '{path}': {inner}")
     };
+    format!("Error: {problem}\nExpected arguments matching this schema:\n{schema}")
+}
 
-    match name {
-        "read_file" => read_file::run(&args),
-        "ls" => ls::run(&args),
-        "write_file" => write_file::run(&args),
-        "edit" => edit::run(&args),
-        "grep" => grep::run(&args),
-        "find" => find::run(&args),
-        "bash" => bash::run(&args),
-        _ => format!("Unknown tool: {name}"),
+/// Run one call: check for cancellation, then dispatch to `tool`, if the
+/// model named one that exists. `Err` carries the failure message.
+pub(crate) fn execute(
+    tool: Option<&dyn DynTool>,
+    name: &str,
+    arguments: &str,
+    ctx: &ToolContext,
+) -> Result<String, String> {
+    if ctx.cancel.is_cancelled() {
+        return Err("Error: tool call cancelled by user".to_string());
     }
+    let Some(tool) = tool else {
+        return Err(format!("Unknown tool: {name}"));
+    };
+    tool.call(arguments, ctx)
 }
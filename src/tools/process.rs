@@ -0,0 +1,101 @@
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+pub(super) struct ProcessOutcome {
+    pub(super) status: ExitStatus,
+    pub(super) stdout: Vec<u8>,
+    pub(super) stderr: Vec<u8>,
+    pub(super) timed_out: bool,
+    pub(super) cancelled: bool,
+}
+
+/// Run `command` in its own process group, capturing stdout and stderr.
+///
+/// The whole group is killed when `timeout` elapses, when `cancel` fires
+/// and once the command itself exits, so grandchildren spawned by a shell
+/// (`sleep 600 &`) neither outlive the tool call nor hold its output pipes
+/// open.
+pub(super) fn run(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    cancel: &CancellationToken,
+) -> io::Result<ProcessOutcome> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+
+    let stdout_thread = child.stdout.take().map(spawn_reader);
+    let stderr_thread = child.stderr.take().map(spawn_reader);
+
+    let start = Instant::now();
+    let mut timed_out = false;
+    let mut cancelled = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            signal_group(&child);
+            break status;
+        }
+        if cancel.is_cancelled() {
+            cancelled = true;
+            break kill_group(&mut child)?;
+        }
+        if let Some(limit) = timeout
+            && start.elapsed() >= limit
+        {
+            timed_out = true;
+            break kill_group(&mut child)?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    Ok(ProcessOutcome {
+        status,
+        stdout: join_reader(stdout_thread),
+        stderr: join_reader(stderr_thread),
+        timed_out,
+        cancelled,
+    })
+}
+
+fn kill_group(child: &mut Child) -> io::Result<ExitStatus> {
+    signal_group(child);
+    let _ = child.kill();
+    child.wait()
+}
+
+/// SIGKILL every process left in the child's group.
+fn signal_group(child: &Child) {
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: `kill` has no memory-safety preconditions; a negative pid
+        // targets the process group created by `process_group(0)` above.
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+}
+
+fn spawn_reader<R>(mut pipe: R) -> JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+fn join_reader(handle: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    handle
+        .and_then(|thread| thread.join().ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,29 @@
+use super::process::run;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+#[test]
+fn backgrounded_children_are_killed_when_the_command_exits() {
+    let start = Instant::now();
+    let outcome = run(
+        Command::new("sh").arg("-c").arg("sleep 600 & echo $!"),
+        Some(Duration::from_secs(30)),
+        &CancellationToken::new(),
+    )
+    .expect("sh should run");
+
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(outcome.status.success());
+    assert!(!outcome.timed_out);
+    let pid = String::from_utf8(outcome.stdout).expect("utf-8 pid");
+    let stat = format!("/proc/{}/stat", pid.trim());
+    // SIGKILL lands asynchronously; once it has, the sleep is gone or at
+    // most a zombie awaiting its reaper.
+    let gone = || std::fs::read_to_string(&stat).map_or(true, |stat| stat.contains(") Z "));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !gone() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(gone(), "{stat} is still running");
+}
@@ -1,51 +1,62 @@
-use super::truncate;
-
-pub fn definition() -> serde_json::Value {
-    serde_json::json!({
-        "type": "function",
-        "name": "read_file",
-        "description": "Read the contents of a file. Output is truncated to 2000 lines or 50KB. Use offset/limit for large files.",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "path": { "type": "string", "description": "The file path to read" },
-                "offset": { "type": "integer", "description": "Line number to start reading from (1-indexed)" },
-                "limit": { "type": "integer", "description": "Maximum number of lines to read" }
-            },
-            "required": ["path"]
-        }
-    })
+use super::{Tool, ToolContext};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub(super) struct ReadFile;
+
+#[derive(Deserialize, JsonSchema)]
+pub(super) struct Args {
+    /// The file path to read
+    path: String,
+    /// Line number to start reading from (1-indexed)
+    offset: Option<usize>,
+    /// Maximum number of lines to read
+    limit: Option<usize>,
 }
 
-pub fn run(args: &serde_json::Value) -> String {
-    let Some(path) = args["path"].as_str() else {
-        return "Error: missing 'path' argument".to_string();
-    };
+impl Tool for ReadFile {
+    type Args = Args;
 
-    let content = match std::fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(e) => return format!("Error: {e}"),
-    };
+    fn name(&self) -> &'static str {
+        "read_file"
+    }
 
-    let lines: Vec<&str> = content.lines().collect();
-    let total_lines = lines.len();
+    fn description(&self) -> &'static str {
+        "Read the contents of a file. Long files are truncated; use offset/limit to read the rest."
+    }
 
-    // Apply offset (1-indexed)
-    let start = args["offset"]
-        .as_u64()
-        .and_then(|o| usize::try_from(o.saturating_sub(1)).ok())
-        .unwrap_or(0);
+    fn is_mutating(&self) -> bool {
+        false
+    }
 
-    if start >= total_lines {
-        return format!("Error: offset {start} is beyond end of file ({total_lines} lines)");
+    fn path<'a>(&self, args: &'a Args) -> Option<&'a str> {
+        Some(&args.path)
     }
 
-    // Apply limit
-    let end = args["limit"]
-        .as_u64()
-        .and_then(|l| usize::try_from(l).ok())
-        .map_or(total_lines, |l| (start + l).min(total_lines));
+    fn run(&self, args: Args, ctx: &ToolContext) -> Result<String, String> {
+        let path = ctx.workspace.check(&args.path)?;
+        let content = std::fs::read_to_string(path).map_err(|e| format!("Error: {e}"))?;
+
+        let lines: Vec<&str> = content.lines().collect();
+        let total_lines = lines.len();
 
-    let selected = lines[start..end].join("\n");
-    truncate::head(&selected, 2000, "lines remaining, use offset to read more")
+        // Apply offset (1-indexed)
+        let start = args.offset.map_or(0, |o| o.saturating_sub(1));
+
+        if start >= total_lines {
+            return Err(format!(
+                "Error: offset {start} is beyond end of file ({total_lines} lines)"
+            ));
+        }
+
+        // Apply limit
+        let end = args
+            .limit
+            .map_or(total_lines, |l| (start + l).min(total_lines));
+
+        let selected = lines[start..end].join("\n");
+        Ok(ctx
+            .limits
+            .head(&selected, "lines remaining, use offset to read more"))
+    }
 }
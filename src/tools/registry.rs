@@ -0,0 +1,61 @@
+use super::bash::Bash;
+use super::edit::Edit;
+use super::find::Find;
+use super::grep::Grep;
+use super::ls::Ls;
+use super::read_file::ReadFile;
+use super::write_file::WriteFile;
+use super::{DynTool, Tool};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// The tools offered to the model, in definition order.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn DynTool>>,
+}
+
+impl ToolRegistry {
+    /// The built-in file, search and shell tools.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(ReadFile);
+        registry.register(Ls);
+        registry.register(WriteFile);
+        registry.register(Edit);
+        registry.register(Grep);
+        registry.register(Find);
+        registry.register(Bash);
+        registry
+    }
+
+    /// Add a tool, replacing any existing tool with the same name.
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.insert(Arc::new(tool));
+    }
+
+    pub(crate) fn insert(&mut self, tool: Arc<dyn DynTool>) {
+        match self.tools.iter_mut().find(|t| t.name() == tool.name()) {
+            Some(existing) => *existing = tool,
+            None => self.tools.push(tool),
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Arc<dyn DynTool>> {
+        self.tools.iter().find(|tool| tool.name() == name)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &dyn DynTool> {
+        self.tools.iter().map(AsRef::as_ref)
+    }
+
+    /// Definitions for every tool, with strict-mode schemas if `strict`.
+    pub fn definitions(&self, strict: bool) -> Vec<Value> {
+        self.iter().map(|tool| tool.definition(strict)).collect()
+    }
+
+    /// Unknown tools fail without side effects, so they count as read-only.
+    pub fn is_mutating(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|tool| tool.is_mutating())
+    }
+}
@@ -0,0 +1,92 @@
+use super::{ToolContext, ToolRegistry, execute};
+
+fn run(name: &str, arguments: &str) -> Result<String, String> {
+    let registry = ToolRegistry::builtin();
+    let tool = registry.get(name).map(AsRef::as_ref);
+    execute(tool, name, arguments, &ToolContext::default())
+}
+
+#[test]
+fn definitions_are_generated_from_argument_types() {
+    let registry = ToolRegistry::builtin();
+    let definitions = registry.definitions(false);
+    let read_file = definitions
+        .iter()
+        .find(|definition| definition["name"] == "read_file")
+        .expect("read_file is built in");
+
+    let parameters = &read_file["parameters"];
+    assert_eq!(parameters["type"], "object");
+    assert_eq!(parameters["required"], serde_json::json!(["path"]));
+    assert_eq!(
+        parameters["properties"]["path"]["description"],
+        "The file path to read"
+    );
+    assert!(parameters.get("$schema").is_none());
+    assert!(parameters.get("title").is_none());
+}
+
+#[test]
+fn invalid_arguments_name_the_field() {
+    let output = run("read_file", r#"{"path":"Cargo.toml","offset":"5"}"#).unwrap_err();
+    assert!(
+        output.starts_with("Error: invalid arguments for read_file: 'offset': invalid type"),
+        "{output}"
+    );
+
+    let output = run("bash", r#"{"command":"true","timeout":0}"#).unwrap_err();
+    assert!(
+        output.starts_with("Error: invalid arguments for bash: 'timeout':"),
+        "{output}"
+    );
+
+    let output = run("edit", r#"{"path":"Cargo.toml"}"#).unwrap_err();
+    assert!(
+        output.starts_with("Error: invalid arguments for edit: missing field `old_text`"),
+        "{output}"
+    );
+}
+
+#[test]
+fn strict_definitions_require_every_property_and_accept_nulls() {
+    let registry = ToolRegistry::builtin();
+    let definitions = registry.definitions(true);
+    let read_file = definitions
+        .iter()
+        .find(|definition| definition["name"] == "read_file")
+        .expect("read_file is built in");
+
+    assert_eq!(read_file["strict"], true);
+    let parameters = &read_file["parameters"];
+    assert_eq!(parameters["additionalProperties"], false);
+    assert_eq!(
+        parameters["required"],
+        serde_json::json!(["limit", "offset", "path"])
+    );
+    assert_eq!(
+        parameters["properties"]["offset"]["type"],
+        serde_json::json!(["integer", "null"])
+    );
+    assert!(parameters["properties"]["offset"].get("format").is_none());
+
+    let output = run(
+        "read_file",
+        r#"{"path":"Cargo.toml","offset":null,"limit":1}"#,
+    );
+    assert_eq!(output.as_deref(), Ok("[package]"));
+}
+
+#[test]
+fn broken_json_reports_position_and_schema() {
+    let output = run("write_file", r#"{"path": tru}"#).unwrap_err();
+
+    let (problem, schema) = output
+        .split_once("\nExpected arguments matching this schema:\n")
+        .expect("error should include the schema");
+    assert_eq!(
+        problem,
+        "Error: invalid JSON in arguments for write_file at line 1 column 13: expected ident"
+    );
+    let schema: serde_json::Value = serde_json::from_str(schema).expect("schema is JSON");
+    assert_eq!(schema["required"], serde_json::json!(["path", "content"]));
+}
@@ -0,0 +1,155 @@
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Arguments that were not valid JSON as sent, and how they were fixed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Repair {
+    pub arguments: String,
+    pub fixes: Vec<&'static str>,
+}
+
+/// Try to turn a malformed `arguments` string into valid JSON: empty
+/// input, raw control characters inside strings, trailing commas and, if
+/// `complete_truncated` is set, output cut off mid-object. Returns `None`
+/// when the input already parses or cannot be repaired.
+///
+/// Completing truncated output guesses where a string ended, so it is only
+/// safe for tools that don't write what they are given.
+pub fn repair_arguments(raw: &str, complete_truncated: bool) -> Option<Repair> {
+    if parses(raw) {
+        return None;
+    }
+    if raw.trim().is_empty() {
+        return Some(Repair {
+            arguments: "{}".to_string(),
+            fixes: vec!["empty arguments"],
+        });
+    }
+
+    let mut fixes = Vec::new();
+    let text = clean(raw, &mut fixes);
+    if parses(&text) {
+        return Some(Repair {
+            arguments: text,
+            fixes,
+        });
+    }
+    if !complete_truncated {
+        return None;
+    }
+
+    // Probably truncated: close whatever is open, dropping trailing members
+    // one at a time if the last one is too incomplete to close.
+    let mut candidate = text.as_str();
+    loop {
+        let closed = close(candidate);
+        if parses(&closed) {
+            fixes.push("completed truncated arguments");
+            return Some(Repair {
+                arguments: closed,
+                fixes,
+            });
+        }
+        candidate = &candidate[..candidate.rfind(',')?];
+    }
+}
+
+fn parses(text: &str) -> bool {
+    serde_json::from_str::<Value>(text).is_ok()
+}
+
+/// Escape control characters inside strings and drop commas directly
+/// before a closing bracket.
+fn clean(raw: &str, fixes: &mut Vec<&'static str>) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for ch in raw.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            } else if ch.is_control() && u32::from(ch) < 0x20 {
+                note(fixes, "escaped control characters in strings");
+                match ch {
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\t' => out.push_str("\\t"),
+                    _ => {
+                        let _ = write!(out, "\\u{:04x}", u32::from(ch));
+                    }
+                }
+                continue;
+            }
+        } else if ch == '"' {
+            in_string = true;
+        } else if (ch == '}' || ch == ']') && drop_trailing_comma(&mut out) {
+            note(fixes, "removed trailing commas");
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Close an unterminated string and every open bracket, completing a
+/// dangling `"key":` with `null`.
+fn close(text: &str) -> String {
+    let mut open = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for ch in text.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = text.to_string();
+    if in_string {
+        if escaped {
+            out.pop();
+        }
+        out.push('"');
+    }
+    drop_trailing_comma(&mut out);
+    if out.ends_with(':') {
+        out.push_str("null");
+    }
+    out.extend(open.iter().rev());
+    out
+}
+
+fn drop_trailing_comma(out: &mut String) -> bool {
+    let trimmed = out.trim_end().len();
+    if out[..trimmed].ends_with(',') {
+        out.truncate(trimmed - 1);
+        true
+    } else {
+        out.truncate(trimmed);
+        false
+    }
+}
+
+fn note(fixes: &mut Vec<&'static str>, fix: &'static str) {
+    if !fixes.contains(&fix) {
+        fixes.push(fix);
+    }
+}
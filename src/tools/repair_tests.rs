@@ -0,0 +1,61 @@
+use super::repair::{Repair, repair_arguments};
+
+fn repaired(raw: &str) -> Repair {
+    repair_arguments(raw, true).expect("arguments should be repairable")
+}
+
+#[test]
+fn valid_arguments_are_left_alone() {
+    assert_eq!(repair_arguments(r#"{"path": "a,b"}"#, true), None);
+}
+
+#[test]
+fn repairs_trailing_commas_and_raw_newlines() {
+    let repair = repaired("{\"path\": \"a.txt\", \"content\": \"one\ntwo\",}");
+
+    assert_eq!(
+        repair.arguments,
+        r#"{"path": "a.txt", "content": "one\ntwo"}"#
+    );
+    assert_eq!(
+        repair.fixes,
+        vec![
+            "escaped control characters in strings",
+            "removed trailing commas"
+        ]
+    );
+}
+
+#[test]
+fn completes_truncated_arguments() {
+    let repair = repaired(r#"{"path": "a.txt", "content": "partial \"quo"#);
+    assert_eq!(
+        repair.arguments,
+        r#"{"path": "a.txt", "content": "partial \"quo"}"#
+    );
+    assert_eq!(repair.fixes, vec!["completed truncated arguments"]);
+
+    let repair = repaired(r#"{"pattern": "fn main", "pa"#);
+    assert_eq!(repair.arguments, r#"{"pattern": "fn main"}"#);
+
+    assert_eq!(repaired(r#"{"path": "#).arguments, r#"{"path":null}"#);
+    assert_eq!(repaired("").arguments, "{}");
+}
+
+#[test]
+fn leaves_truncated_arguments_alone_unless_asked() {
+    let truncated = r#"{"path": "a.txt", "content": "partial"#;
+    assert_eq!(repair_arguments(truncated, false), None);
+
+    let repair = repair_arguments("{\"content\": \"one\ntwo\",}", false);
+    assert_eq!(
+        repair.map(|repair| repair.arguments),
+        Some(r#"{"content": "one\ntwo"}"#.to_string())
+    );
+}
+
+#[test]
+fn gives_up_on_unrecoverable_input() {
+    assert_eq!(repair_arguments(r#"{"path": tru"#, true), None);
+    assert_eq!(repair_arguments("not json", true), None);
+}
@@ -0,0 +1,52 @@
+use schemars::JsonSchema;
+use schemars::generate::SchemaSettings;
+use serde_json::Value;
+
+/// The `parameters` schema for a tool's argument type: a self-contained
+/// object schema without the `$schema`, `title` and doc-comment
+/// `description` that schemars adds at the root. With `strict`, the schema
+/// is rewritten into the form strict function calling accepts.
+pub(super) fn parameters<T: JsonSchema>(strict: bool) -> Value {
+    let generator = SchemaSettings::draft2020_12()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings.meta_schema = None;
+        })
+        .into_generator();
+    let mut schema = generator.into_root_schema_for::<T>().to_value();
+    if let Some(root) = schema.as_object_mut() {
+        root.remove("title");
+        root.remove("description");
+    }
+    if strict {
+        make_strict(&mut schema);
+    }
+    schema
+}
+
+/// Require every property and forbid extra ones. `Option` fields are
+/// already nullable, so the model passes `null` for values it would have
+/// left out. `format` is dropped from non-string schemas, where strict
+/// mode rejects it.
+fn make_strict(schema: &mut Value) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+    if let Some(Value::Object(properties)) = object.get_mut("properties") {
+        let names = properties.keys().cloned().map(Value::String).collect();
+        properties.values_mut().for_each(make_strict);
+        object.insert("required".to_string(), Value::Array(names));
+        object.insert("additionalProperties".to_string(), Value::Bool(false));
+    }
+    if object.get("type").and_then(Value::as_str) != Some("string") {
+        object.remove("format");
+    }
+    if let Some(items) = object.get_mut("items") {
+        make_strict(items);
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let Some(Value::Array(variants)) = object.get_mut(key) {
+            variants.iter_mut().for_each(make_strict);
+        }
+    }
+}
@@ -1,57 +1,82 @@
-const MAX_LINES: usize = 2000;
-const MAX_BYTES: usize = 50 * 1024; // 50KB
+use crate::config::Config;
 
-/// Keep the first `max_lines` lines or `MAX_BYTES`, whichever hits first.
-pub fn head(text: &str, max_lines: usize, label: &str) -> String {
-    let lines: Vec<&str> = text.lines().collect();
+/// Caps applied to tool output before it goes back to the model.
+#[derive(Clone, Copy, Debug)]
+pub struct OutputLimits {
+    pub max_lines: usize,
+    pub max_bytes: usize,
+}
 
-    if lines.len() <= max_lines && text.len() <= MAX_BYTES {
-        return text.to_string();
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_lines: 2000,
+            max_bytes: 50 * 1024, // 50KB
+        }
     }
+}
 
-    let mut byte_count = 0;
-    let mut line_count = 0;
-
-    for line in &lines {
-        let line_bytes = byte_count + line.len() + 1; // +1 for newline
-        if line_count >= max_lines || line_bytes > MAX_BYTES {
-            break;
+impl OutputLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_lines: config.usize("tools.max_output_lines"),
+            max_bytes: config.usize("tools.max_output_bytes"),
         }
-        byte_count = line_bytes;
-        line_count += 1;
     }
 
-    let truncated = &text[..byte_count];
-    let remaining = lines.len() - line_count;
-    format!("{truncated}\n... truncated ({remaining} {label})")
-}
+    /// Keep the first `max_lines` lines or `max_bytes`, whichever hits
+    /// first. Used for file contents and listings.
+    pub fn head(self, text: &str, label: &str) -> String {
+        let lines: Vec<&str> = text.lines().collect();
+
+        if lines.len() <= self.max_lines && text.len() <= self.max_bytes {
+            return text.to_string();
+        }
 
-/// Keep the last N lines / bytes. Used for command output.
-pub fn tail(text: &str) -> String {
-    let lines: Vec<&str> = text.lines().collect();
+        let mut byte_count = 0;
+        let mut line_count = 0;
 
-    if lines.len() <= MAX_LINES && text.len() <= MAX_BYTES {
-        return text.to_string();
+        for line in &lines {
+            let line_bytes = byte_count + line.len() + 1; // +1 for newline
+            if line_count >= self.max_lines || line_bytes > self.max_bytes {
+                break;
+            }
+            byte_count = line_bytes;
+            line_count += 1;
+        }
+
+        let truncated = &text[..byte_count];
+        let remaining = lines.len() - line_count;
+        format!("{truncated}\n... truncated ({remaining} {label})")
     }
 
-    // Start from the end, collect lines until we hit limits
-    let mut byte_count = 0;
-    let mut line_count = 0;
+    /// Keep the last N lines / bytes. Used for command output.
+    pub fn tail(self, text: &str) -> String {
+        let lines: Vec<&str> = text.lines().collect();
 
-    for line in lines.iter().rev() {
-        let line_bytes = byte_count + line.len() + 1; // +1 for newline
-        if line_count >= MAX_LINES || line_bytes > MAX_BYTES {
-            break;
+        if lines.len() <= self.max_lines && text.len() <= self.max_bytes {
+            return text.to_string();
         }
-        byte_count = line_bytes;
-        line_count += 1;
-    }
 
-    let omitted = lines.len() - line_count;
-    let kept = &lines[omitted..];
+        // Start from the end, collect lines until we hit limits
+        let mut byte_count = 0;
+        let mut line_count = 0;
 
-    format!(
-        "... truncated ({omitted} lines omitted)\n{}",
-        kept.join("\n")
-    )
+        for line in lines.iter().rev() {
+            let line_bytes = byte_count + line.len() + 1; // +1 for newline
+            if line_count >= self.max_lines || line_bytes > self.max_bytes {
+                break;
+            }
+            byte_count = line_bytes;
+            line_count += 1;
+        }
+
+        let omitted = lines.len() - line_count;
+        let kept = &lines[omitted..];
+
+        format!(
+            "... truncated ({omitted} lines omitted)\n{}",
+            kept.join("\n")
+        )
+    }
 }
@@ -0,0 +1,175 @@
+use crate::config::Config;
+use anyhow::{Context, Result, bail};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+/// Symlinks followed while resolving one path, as in the kernel.
+const MAX_SYMLINKS: usize = 40;
+
+/// What happens when a file tool's path resolves outside every root, from
+/// `workspace.outside`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutsideAccess {
+    /// Ask the user before the call runs (`ask`).
+    Ask,
+    /// Refuse the call (`deny`).
+    Deny,
+    /// No confinement (`allow`).
+    Allow,
+}
+
+/// The directories the file tools may use: the git root (or the working
+/// directory outside a repository) plus `workspace.roots`.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    roots: Vec<PathBuf>,
+    outside: OutsideAccess,
+}
+
+/// No roots and no confinement, for a bare [`ToolContext`](super::ToolContext).
+impl Default for Workspace {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            outside: OutsideAccess::Allow,
+        }
+    }
+}
+
+impl Workspace {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let outside = match config.string("workspace.outside").unwrap_or_default() {
+            "ask" => OutsideAccess::Ask,
+            "deny" => OutsideAccess::Deny,
+            "allow" => OutsideAccess::Allow,
+            other => bail!("unknown workspace.outside '{other}' (expected ask, deny or allow)"),
+        };
+        let cwd = std::env::current_dir().context("failed to read the working directory")?;
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let mut roots = vec![Self::default_root()?];
+        for (root, _) in config.list("workspace.roots") {
+            roots.push(match (root.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => cwd.join(root),
+            });
+        }
+        Ok(Self::new(roots, outside))
+    }
+
+    /// The git repository containing the working directory, or the working
+    /// directory itself outside a repository.
+    pub fn default_root() -> Result<PathBuf> {
+        let cwd = std::env::current_dir().context("failed to read the working directory")?;
+        Ok(git_root(&cwd).unwrap_or(cwd))
+    }
+
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>, outside: OutsideAccess) -> Self {
+        Self {
+            roots: roots.into_iter().map(|root| real_path(&root)).collect(),
+            outside,
+        }
+    }
+
+    pub const fn outside_access(&self) -> OutsideAccess {
+        self.outside
+    }
+
+    /// The same roots with access outside them allowed, for a call the
+    /// user approved.
+    #[must_use]
+    pub fn allowing_outside(&self) -> Self {
+        Self {
+            roots: self.roots.clone(),
+            outside: OutsideAccess::Allow,
+        }
+    }
+
+    /// Resolve `path` against the working directory, following symlinks
+    /// and `..` the way the kernel would. `Err` holds the resolved path
+    /// when it lies outside every root and access there isn't allowed.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, PathBuf> {
+        let resolved = real_path(Path::new(path));
+        if self.outside == OutsideAccess::Allow
+            || self.roots.iter().any(|root| resolved.starts_with(root))
+        {
+            Ok(resolved)
+        } else {
+            Err(resolved)
+        }
+    }
+
+    /// [`resolve`](Self::resolve), with the error the model sees.
+    pub(crate) fn check(&self, path: &str) -> Result<PathBuf, String> {
+        self.resolve(path)
+            .map_err(|resolved| self.refusal(path, &resolved))
+    }
+
+    pub(crate) fn refusal(&self, path: &str, resolved: &Path) -> String {
+        let roots: Vec<String> = self
+            .roots
+            .iter()
+            .map(|root| root.display().to_string())
+            .collect();
+        let reason = match self.outside {
+            OutsideAccess::Deny => "workspace.outside = \"deny\" refuses access there",
+            _ => "access there needs the user's approval",
+        };
+        format!(
+            "Error: {path} resolves to {}, which is outside the workspace ({}); {reason}",
+            resolved.display(),
+            roots.join(", ")
+        )
+    }
+}
+
+/// The nearest ancestor of `dir` that contains `.git`.
+fn git_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// An absolute path with every symlink and `.`/`..` resolved, like
+/// `realpath -m`: components that don't exist yet are kept as written, so
+/// a file about to be created resolves to where it will be created.
+pub fn real_path(path: &Path) -> PathBuf {
+    let absolute =
+        std::env::current_dir().map_or_else(|_| path.to_path_buf(), |cwd| cwd.join(path));
+    // Components still to walk, last one first.
+    let mut pending: Vec<OsString> = Vec::new();
+    push_components(&mut pending, &absolute);
+    let mut resolved = PathBuf::from("/");
+    let mut symlinks = 0;
+    while let Some(component) = pending.pop() {
+        match component.to_str() {
+            Some("/") => resolved = PathBuf::from("/"),
+            Some(".") => {}
+            Some("..") => {
+                resolved.pop();
+            }
+            _ => {
+                let next = resolved.join(&component);
+                match std::fs::read_link(&next) {
+                    Ok(target) if symlinks < MAX_SYMLINKS => {
+                        symlinks += 1;
+                        push_components(&mut pending, &target);
+                    }
+                    _ => resolved = next,
+                }
+            }
+        }
+    }
+    resolved
+}
+
+fn push_components(pending: &mut Vec<OsString>, path: &Path) {
+    for component in path.components().rev() {
+        pending.push(match component {
+            Component::RootDir => "/".into(),
+            Component::CurDir => ".".into(),
+            Component::ParentDir => "..".into(),
+            Component::Normal(name) => name.to_os_string(),
+            Component::Prefix(prefix) => prefix.as_os_str().to_os_string(),
+        });
+    }
+}
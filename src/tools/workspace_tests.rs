@@ -0,0 +1,116 @@
+use super::{OutsideAccess, ToolContext, ToolRegistry, Workspace};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory holding `workspace/` and, next to it, `outside/`.
+fn scratch() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ox-workspace-{:016x}", fastrand::u64(..)));
+    std::fs::create_dir_all(dir.join("workspace/src")).expect("create workspace");
+    std::fs::create_dir_all(dir.join("outside")).expect("create outside dir");
+    std::fs::write(dir.join("outside/secret.txt"), "secret").expect("write secret");
+    std::fs::write(dir.join("workspace/src/main.rs"), "fn main() {}").expect("write file");
+    dir
+}
+
+fn run(workspace: &Workspace, name: &str, arguments: &serde_json::Value) -> String {
+    let ctx = ToolContext {
+        workspace: workspace.clone(),
+        ..ToolContext::default()
+    };
+    ToolRegistry::builtin()
+        .get(name)
+        .expect("tool is built in")
+        .call(&arguments.to_string(), &ctx)
+        .unwrap_or_else(|error| error)
+}
+
+fn path(path: &Path) -> String {
+    path.display().to_string()
+}
+
+#[test]
+fn dot_dot_traversal_is_resolved_before_checking() {
+    let dir = scratch();
+    let workspace = Workspace::new([dir.join("workspace")], OutsideAccess::Deny);
+    let root = workspace
+        .resolve(&path(&dir.join("workspace")))
+        .expect("inside");
+
+    assert_eq!(
+        workspace.resolve(&path(&dir.join("workspace/src/../src/main.rs"))),
+        Ok(root.join("src/main.rs"))
+    );
+    assert_eq!(
+        workspace.resolve(&path(&dir.join("workspace/new/dir/../file.txt"))),
+        Ok(root.join("new/file.txt"))
+    );
+    let escape = dir.join("workspace/src/../../outside/secret.txt");
+    let resolved = workspace.resolve(&path(&escape)).expect_err("outside");
+    assert!(resolved.ends_with("outside/secret.txt"), "{resolved:?}");
+
+    let output = run(
+        &workspace,
+        "read_file",
+        &serde_json::json!({ "path": escape }),
+    );
+    assert!(
+        output.starts_with(&format!(
+            "Error: {} resolves to {}, which is outside the workspace ({})",
+            escape.display(),
+            resolved.display(),
+            root.display()
+        )),
+        "{output}"
+    );
+    let output = run(&workspace, "ls", &serde_json::json!({ "path": "/" }));
+    assert!(output.contains("workspace.outside = \"deny\""), "{output}");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn symlinks_out_of_the_workspace_are_caught() {
+    let dir = scratch();
+    let root = dir.join("workspace");
+    symlink(dir.join("outside/secret.txt"), root.join("link.txt")).expect("file symlink");
+    symlink(dir.join("outside"), root.join("linked_dir")).expect("dir symlink");
+    symlink("../outside/new.txt", root.join("dangling.txt")).expect("dangling symlink");
+    symlink(root.join("src"), root.join("inner")).expect("inner symlink");
+    let workspace = Workspace::new([root.clone()], OutsideAccess::Deny);
+
+    for escape in ["link.txt", "linked_dir/secret.txt", "linked_dir/new.txt"] {
+        let output = run(
+            &workspace,
+            "read_file",
+            &serde_json::json!({ "path": root.join(escape) }),
+        );
+        assert!(
+            output.contains("outside the workspace"),
+            "{escape}: {output}"
+        );
+    }
+    let output = run(
+        &workspace,
+        "write_file",
+        &serde_json::json!({ "path": root.join("dangling.txt"), "content": "x" }),
+    );
+    assert!(output.contains("outside the workspace"), "{output}");
+    assert!(!dir.join("outside/new.txt").exists());
+
+    let output = run(
+        &workspace,
+        "read_file",
+        &serde_json::json!({ "path": root.join("inner/main.rs") }),
+    );
+    assert_eq!(output, "fn main() {}");
+
+    let allowed = Workspace::new([root, dir.join("outside")], OutsideAccess::Deny);
+    let output = run(
+        &allowed,
+        "read_file",
+        &serde_json::json!({ "path": dir.join("workspace/link.txt") }),
+    );
+    assert_eq!(output, "secret");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
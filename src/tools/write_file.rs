@@ -1,28 +1,45 @@
-pub fn definition() -> serde_json::Value {
-    serde_json::json!({
-        "type": "function",
-        "name": "write_file",
-        "description": "Write content to a file, creating it if it doesn't exist or overwriting if it does",
-        "parameters": {
-            "type": "object",
-            "properties": {
-                "path": { "type": "string", "description": "The file path to write to" },
-                "content": { "type": "string", "description": "The content to write to the file" }
-            },
-            "required": ["path", "content"]
-        }
-    })
+use super::{Tool, ToolContext, diff};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub(super) struct WriteFile;
+
+#[derive(Deserialize, JsonSchema)]
+pub(super) struct Args {
+    /// The file path to write to
+    path: String,
+    /// The content to write to the file
+    content: String,
 }
 
-pub fn run(args: &serde_json::Value) -> String {
-    let Some(path) = args["path"].as_str() else {
-        return "Error: missing 'path' argument".to_string();
-    };
-    let Some(content) = args["content"].as_str() else {
-        return "Error: missing 'content' argument".to_string();
-    };
-    match std::fs::write(path, content) {
-        Ok(()) => format!("Successfully wrote to {path}"),
-        Err(e) => format!("Error: {e}"),
+impl Tool for WriteFile {
+    type Args = Args;
+
+    fn name(&self) -> &'static str {
+        "write_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Write content to a file, creating it if it doesn't exist or overwriting if it does"
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn preview(&self, args: &Args) -> Option<String> {
+        let old = std::fs::read_to_string(&args.path).unwrap_or_default();
+        Some(diff::preview(&args.path, &old, &args.content))
+    }
+
+    fn path<'a>(&self, args: &'a Args) -> Option<&'a str> {
+        Some(&args.path)
+    }
+
+    fn run(&self, args: Args, ctx: &ToolContext) -> Result<String, String> {
+        let path = ctx.workspace.check(&args.path)?;
+        std::fs::write(path, args.content)
+            .map(|()| format!("Successfully wrote to {}", args.path))
+            .map_err(|e| format!("Error: {e}"))
     }
 }